@@ -0,0 +1,367 @@
+/*
+ * Generador de arenas aleatorias para el modo sin fin. A partir de una semilla y de los parámetros de dificultad
+ * se arma un mapa con el formato de los archivos de mapas: el suelo en la última fila, plataformas, decoración
+ * en el cielo y en el suelo y, en las dificultades bajas, algunos objetos que dan una vida sobre las
+ * plataformas. Las celdas usan la lista de sprites de map0.txt (el de assets, así que --assets también cambia
+ * las arenas) y se eligen por sus propiedades (ver tile.rs): el suelo es el primer sprite solid que no hace
+ * daño, los objetos el primero con pickup=life y la decoración los que tienen decor=sky y decor=ground. Si el
+ * map0.txt de assets no tiene un sprite sólido se usa el incluido en el binario.
+ *
+ * Cada plataforma se agrega sólo si todas las plataformas siguen siendo alcanzables desde el punto donde
+ * aparece el zorro. reachable simula al zorro con las funciones de physics.rs que usa Character en cada
+ * update (walk, rise, fall_speed y descend): desde el suelo puede caminar o saltar, y en cada update en el
+ * aire puede moverse a cualquier lado o quedarse quieto y, al subir, mantener o soltar la flecha ARRIBA.
+ * Los estados visitados de la simulación se guardan en Search, que generate reutiliza al probar cada plataforma.
+ *
+ * La dificultad reduce el número y el largo de las plataformas y quita los MedKit; además, en el modo sin fin
+ * (Campaign::endless) aumenta la meta de manzanas y la frecuencia de las rocas de lava de cada arena.
+ */
+
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use crate::assets::Assets;
+use crate::character::Character;
+use crate::gamemap::{GameMap, MAX_SPRITES};
+use crate::physics::{cell, descend, fall_speed, rise, walk, CUT_SPEED, JUMP_SPEED, TERMINAL_SPEED, UNITS, WALK_SPEED};
+use crate::random::{RandomSource, XorShift};
+use crate::tile::{Decor, Pickup, TileProperties};
+
+struct Tiles {                                                              // Sprites de la plantilla que usa la arena
+    floor: u8,
+    medkit: Option<u8>,
+    sky: Option<u8>,
+    ground: Option<u8>,
+}
+
+impl Tiles {
+    fn find(map: &GameMap) -> Option<Tiles> {                               // Sin un sprite sólido no se puede armar el suelo
+        let tile = |wanted: &dyn Fn(&TileProperties) -> bool| {
+            map.tiles.iter().take(MAX_SPRITES).position(wanted).map(|i| i as u8 + 1)
+        };
+        Some(Tiles {
+            floor: tile(&|props| props.solid && !props.hazard)?,
+            medkit: tile(&|props| props.pickup == Some(Pickup::Life)),
+            sky: tile(&|props| props.decor == Some(Decor::Sky)),
+            ground: tile(&|props| props.decor == Some(Decor::Ground)),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+enum Motion {                                                               // Lo que hace el zorro en la simulación, como los estados de Character
+    Stand,
+    Rise,
+    Fall,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ArenaParams {
+    pub width: u32,
+    pub height: u32,
+    pub wrap: bool,
+    pub difficulty: u32,
+}
+
+impl Default for ArenaParams {
+    fn default() -> Self {
+        ArenaParams {
+            width: 20,
+            height: 10,
+            wrap: true,
+            difficulty: 0,
+        }
+    }
+}
+
+impl ArenaParams {
+    pub fn with_difficulty(&self, difficulty: u32) -> ArenaParams {
+        ArenaParams { difficulty, ..*self }
+    }
+
+    fn size(&self) -> (u32, u32) {                                          // Tamaño de la arena, con el mínimo que necesitan el suelo y el HUD
+        (self.width.max(4), self.height.max(6))
+    }
+
+    fn platforms(&self) -> u32 {                                            // Número de plataformas que se intentan colocar
+        let (width, height) = self.size();
+        (width * (height - 4) / 24).saturating_sub(self.difficulty / 2).max(2)
+    }
+
+    fn platform_len(&self) -> (u32, u32) {                                  // Largo mínimo y máximo de una plataforma
+        let max = 5u32.saturating_sub(self.difficulty / 3).max(2);
+        (2, max.min(self.size().0))
+    }
+
+    fn medkits(&self) -> u32 {
+        2u32.saturating_sub(self.difficulty / 3)
+    }
+
+    pub fn goal(&self) -> u16 {                                             // Manzanas para pasar a la siguiente arena
+        10 + self.difficulty.min(20) as u16
+    }
+
+    pub fn ice_rate(&self) -> usize {                                       // Iteraciones entre cada roca de lava
+        20usize.saturating_sub(2 * self.difficulty as usize).max(6)
+    }
+}
+
+pub fn generate(seed: u64, params: &ArenaParams, assets: &Assets) -> GameMap { // generate_text escribe todas las filas del mismo ancho
+    GameMap::from_text(generate_text(seed, params, assets)).unwrap_or_default()
+}
+                                                                            // Arena en el formato de los archivos de mapas
+pub fn generate_text(seed: u64, params: &ArenaParams, assets: &Assets) -> String {
+    let mut template = assets.text("map0.txt");
+    let mut map = GameMap::from_text(template.clone()).unwrap_or_default(); // Un mapa vacío no tiene sprites, se usa el incluido
+    let tiles = match Tiles::find(&map) {
+        Some(tiles) => tiles,
+        None => {
+            template = Assets::embedded().text("map0.txt");
+            map = GameMap::from_text(template.clone()).unwrap_or_default();
+            match Tiles::find(&map) {
+                Some(tiles) => tiles,
+                None => return template,
+            }
+        },
+    };
+    let (width, height) = params.size();
+    map.wrap = params.wrap;
+    map.viewport = None;
+    map.map = vec![vec![0; width as usize]; height as usize];
+    for tile in map.map[height as usize - 1].iter_mut() {
+        *tile = tiles.floor;
+    }
+
+    let mut random = XorShift::new(seed);
+    let (min_len, max_len) = params.platform_len();
+    let mut search = Search::default();                                     // Se prueba cada plataforma con la misma memoria
+    let mut placed = 0;
+    let mut attempts = 0;
+    while placed < params.platforms() && attempts < params.platforms() * 30 {
+        attempts += 1;
+        let y = 2 + random.range(height - 4);                               // Ni en la fila del HUD ni en la fila donde se camina
+        let len = min_len + random.range(max_len - min_len + 1);
+        let x = random.range(width - len + 1);
+        if !free_around(&map, x, y, len) {
+            continue;
+        }
+        for i in x..x + len {
+            map.map[y as usize][i as usize] = tiles.floor;
+        }
+        if search.unreachable(&map).is_empty() {
+            placed += 1;
+        } else {
+            for i in x..x + len {
+                map.map[y as usize][i as usize] = 0;
+            }
+        }
+    }
+
+    let spawn = Character::spawn_coord(width, height);
+    let standing = search.reachable(&map, (spawn.0, spawn.1));
+    let mut spots: Vec<(u32, u32)> = Vec::new();                            // Celdas sobre las plataformas, sin contar el suelo
+    for y in 1..height - 2 {
+        for x in 0..width {
+            if standing[y as usize][x as usize] && map.map[y as usize][x as usize] == 0 {
+                spots.push((x, y));
+            }
+        }
+    }
+    for _ in 0..params.medkits() {
+        if spots.is_empty() {
+            break;
+        }
+        let (x, y) = spots.remove(random.range(spots.len() as u32) as usize);
+        if let Some(medkit) = tiles.medkit {
+            map.map[y as usize][x as usize] = medkit;
+        }
+    }
+
+    for _ in 0..width / 4 {
+        let (x, y) = (random.range(width), 1 + random.range(2));
+        if let Some(sky) = tiles.sky.filter(|_| map.map[y as usize][x as usize] == 0 && !map.properties(x, y + 1).solid) {
+            map.map[y as usize][x as usize] = sky;
+        }
+    }
+    for _ in 0..width / 5 {
+        let x = random.range(width);
+        if let Some(ground) = tiles.ground.filter(|_| x != spawn.0 && map.map[spawn.1 as usize][x as usize] == 0) {
+            map.map[spawn.1 as usize][x as usize] = ground;
+        }
+    }
+
+    to_text(&map, &template)
+}
+
+fn free_around(map: &GameMap, x: u32, y: u32, len: u32) -> bool {          // Una plataforma no puede tocar a otra, ni de lado ni arriba o abajo
+    let width = map.width() as i64;
+    for j in (y - 1)..=(y + 1) {
+        for i in (x as i64 - 1)..=(x + len) as i64 {
+            let i = if map.wrap { (i + width) % width } else { i };
+            if i >= 0 && i < width && map.map[j as usize][i as usize] != 0 {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn to_text(map: &GameMap, template: &str) -> String {                      // Arma el archivo del mapa usando los sprites de la plantilla
+    let mut text = String::new();
+    let _ = writeln!(text, "BackgroundStart\n{}\nBackgroundEnd\n", map.bg);
+    let _ = writeln!(text, "BackTempStart\n{}\nBackTempEnd\n", map.bg_temp);
+    let _ = writeln!(text, "OptionsStart\nWrap:{}\nOptionsEnd\n", map.wrap);
+    let _ = writeln!(text, "MapStart");
+    for row in map.map.iter() {
+        let line: String = row.iter().map(|t| match t {
+            0..=9 => (b'0' + t) as char,
+            _ => (b'A' + t - 10) as char,
+        }).collect();
+        let _ = writeln!(text, "{}", line);
+    }
+    let _ = writeln!(text, "MapEnd\n");
+    if let Some(start) = template.find("SpriteStart") {
+        text.push_str(&template[start..]);
+    }
+    text
+}
+
+                                                                            // Celdas en las que el zorro puede quedar parado partiendo de start
+pub fn reachable(map: &GameMap, start: (u32, u32)) -> Vec<Vec<bool>> {
+    Search::default().reachable(map, start)
+}
+                                                                            // Celdas sobre el suelo o sobre una plataforma a las que no se puede llegar
+pub fn unreachable(map: &GameMap) -> Vec<(u32, u32)> {
+    Search::default().unreachable(map)
+}
+
+#[derive(Debug, Default)]
+pub struct Search {                                                         // Estados ya visitados, se reutilizan entre búsquedas
+    seen: Vec<bool>,
+    visited: Vec<usize>,                                                    // Índices marcados en seen, para limpiar sólo esos
+}
+
+impl Search {
+    pub fn reachable(&mut self, map: &GameMap, start: (u32, u32)) -> Vec<Vec<bool>> {
+        for index in self.visited.drain(..) {
+            self.seen[index] = false;
+        }
+        search(map, start, &mut self.seen, &mut self.visited)
+    }
+
+    pub fn unreachable(&mut self, map: &GameMap) -> Vec<(u32, u32)> {
+        let spawn = Character::spawn_coord(map.width(), map.height());
+        let standing = self.reachable(map, (spawn.0, spawn.1));
+        let mut cells = Vec::new();
+        for y in 0..map.height().saturating_sub(1) {
+            for x in 0..map.width() {
+                if map.properties(x, y + 1).solid && !map.properties(x, y).solid && !standing[y as usize][x as usize] {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+}
+
+fn search(map: &GameMap, start: (u32, u32), seen: &mut Vec<bool>, visited: &mut Vec<usize>) -> Vec<Vec<bool>> {
+    let (width, height) = (map.width(), map.height());
+    let bottom = height as i32 * UNITS;
+    let solid_below = |x: i32, row: u32| map.properties(cell(x) % width, row + 1).solid;
+    let mut standing = vec![vec![false; width as usize]; height as usize];
+    let speeds = (JUMP_SPEED + TERMINAL_SPEED + 1) as usize;                 // Las velocidades van de -JUMP_SPEED a TERMINAL_SPEED
+    let columns = (width as i32 * UNITS / WALK_SPEED) as usize;             // Al caminar se avanza WALK_SPEED unidades
+    let size = columns * bottom as usize * speeds * 3;
+    if seen.len() != size {
+        seen.clear();
+        seen.resize(size, false);
+    }
+    let mut queue = VecDeque::new();
+    queue.push_back((start.0 as i32 * UNITS, start.1 as i32 * UNITS, 0, Motion::Fall));
+
+    while let Some((x, y, vel, motion)) = queue.pop_front() {               // Posición y velocidad en unidades, como en Character
+        if y >= bottom {
+            continue;
+        }
+        let index = ((motion as usize * bottom as usize + y as usize) * speeds + (vel + JUMP_SPEED) as usize) * columns
+            + (x / WALK_SPEED) as usize;
+        if seen[index] {
+            continue;
+        }
+        seen[index] = true;
+        visited.push(index);
+        match motion {
+            Motion::Stand => {
+                standing[cell(y) as usize][(cell(x) % width) as usize] = true;
+                for dx in [-1, 1].iter() {                                  // Caminar, y caer si ya no hay suelo
+                    let next = walk(x, *dx, width, map.wrap);
+                    let motion = if solid_below(next, cell(y)) { Motion::Stand } else { Motion::Fall };
+                    queue.push_back((next, y, 0, motion));
+                }
+                queue.push_back((x, y, -JUMP_SPEED, Motion::Rise));
+            },
+            Motion::Rise => {
+                for dx in -1..=1 {
+                    for held in [true, false].iter() {                      // Al soltar ARRIBA se recorta la velocidad
+                        let vel = if *held { vel } else { vel.max(-CUT_SPEED) };
+                        let (next_y, next_vel) = rise(y, vel, *held);
+                        let motion = if next_vel == 0 { Motion::Fall } else { Motion::Rise };
+                        queue.push_back((walk(x, dx, width, map.wrap), next_y, next_vel, motion));
+                    }
+                }
+            },
+            Motion::Fall => {
+                let landed = y % UNITS == 0 && solid_below(x, cell(y));
+                for dx in -1..=1 {
+                    let next_x = if landed { x } else { walk(x, dx, width, map.wrap) };
+                    let next_vel = fall_speed(vel);
+                    let (next_y, landed) = descend(y, next_vel, |row| solid_below(next_x, row));
+                    match landed {
+                        true => queue.push_back((next_x, next_y, 0, Motion::Stand)),
+                        false => queue.push_back((next_x, next_y, next_vel, Motion::Fall)),
+                    }
+                }
+            },
+        }
+    }
+    standing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_platform_is_reachable() {
+        let assets = Assets::embedded();
+        for difficulty in [0, 3, 8] {
+            for seed in 1..5 {
+                let map = generate(seed, &ArenaParams::default().with_difficulty(difficulty), &assets);
+                assert_eq!(unreachable(&map), Vec::new(), "semilla {} dificultad {}", seed, difficulty);
+            }
+        }
+    }
+
+    #[test]
+    fn finds_unreachable_platforms() {
+        let assets = Assets::embedded();
+        let mut map = generate(1, &ArenaParams::default(), &assets);
+        let last = map.height() as usize - 1;
+        let floor = map.map[last][0];
+        for row in map.map.iter_mut().take(last) {
+            row.iter_mut().for_each(|tile| *tile = 0);
+        }
+        map.map[1][3] = floor;                                              // Ningún salto llega a la fila 0
+        assert_eq!(unreachable(&map), vec![(3, 0)]);
+    }
+
+    #[test]
+    fn tiny_sizes_are_clamped() {
+        let assets = Assets::embedded();
+        for (width, height) in [(0, 0), (1, 1), (3, 20), (40, 2)] {
+            let params = ArenaParams { width, height, ..ArenaParams::default() };
+            let map = generate(7, &params, &assets);
+            assert_eq!((map.width(), map.height()), params.size());
+        }
+    }
+}
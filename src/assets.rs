@@ -0,0 +1,176 @@
+/*
+ * Archivos de texto del juego (sprites, mapas, niveles y temas). Todos los archivos de src/files se incluyen en el
+ * binario con include_str!, así el juego siempre tiene una versión correcta de cada uno, pero también se
+ * pueden cargar en tiempo de ejecución para probar un mapa o un nivel sin volver a compilar: la interfaz web
+ * los descarga con fetch del servidor y la terminal los lee de un directorio (--assets).
+ *
+ * Assets guarda los archivos que se cargaron y regresa el incluido en el binario para los demás. Un archivo
+ * sólo se acepta si pasa la revisión de validate.rs (que también revisa que estén los sprites que el juego
+ * usa de cada archivo); si no, se conservan la versión incluida y los errores para mostrarlos. Los niveles se
+ * revisan al final, con los mapas ya cargados, porque levels.txt puede usar mapas nuevos:
+ *   1. se obtiene levels.txt y con referenced_maps los nombres de sus mapas
+ *   2. se cargan los archivos de FILES y los mapas nuevos con insert
+ *   3. se carga levels.txt con insert
+ * load_dir hace estos pasos leyendo los archivos de un directorio.
+ *
+ * Watcher revisa la fecha de modificación de los archivos .txt de un directorio para recargarlos mientras se
+ * juega (Character::reload cambia los mapas y los sprites sin reiniciar la partida).
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::validate::{self, FileError};
+
+pub const FILES: [&str; 11] = [                                             // Archivos incluidos en el binario
+    "fox_sprites.txt",
+    "levels.txt",
+    "map0.txt",
+    "map1.txt",
+    "map2.txt",
+    "mapStart.txt",
+    "mapEnd.txt",
+    "mapPause.txt",
+    "mapLevel.txt",
+    "mapVictory.txt",
+    "themes.txt",
+];
+
+pub fn embedded(name: &str) -> Option<&'static str> {                      // Archivo incluido en el binario, por nombre
+    match name {
+        "fox_sprites.txt" => Some(include_str!("files/fox_sprites.txt")),
+        "levels.txt" => Some(include_str!("files/levels.txt")),
+        "map0.txt" => Some(include_str!("files/map0.txt")),
+        "map1.txt" => Some(include_str!("files/map1.txt")),
+        "map2.txt" => Some(include_str!("files/map2.txt")),
+        "mapStart.txt" => Some(include_str!("files/mapStart.txt")),
+        "mapEnd.txt" => Some(include_str!("files/mapEnd.txt")),
+        "mapPause.txt" => Some(include_str!("files/mapPause.txt")),
+        "mapLevel.txt" => Some(include_str!("files/mapLevel.txt")),
+        "mapVictory.txt" => Some(include_str!("files/mapVictory.txt")),
+        "themes.txt" => Some(include_str!("files/themes.txt")),
+        _ => None,
+    }
+}
+
+pub fn referenced_maps(levels: &str) -> Vec<String> {                       // Mapas de una lista de niveles que no están incluidos en el binario
+    let mut maps: Vec<String> = Vec::new();
+    for line in levels.lines() {
+        if let Some(name) = line.trim().strip_prefix("Map:").map(|n| n.trim()) {
+            if embedded(name).is_none() && !maps.iter().any(|m| m == name) {
+                maps.push(name.to_string());
+            }
+        }
+    }
+    maps
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Assets {
+    loaded: HashMap<String, String>,
+}
+
+impl Assets {
+    pub fn embedded() -> Assets {                                           // Sólo los archivos incluidos en el binario
+        Assets::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {                         // El archivo cargado o, si no se cargó, el incluido
+        match self.loaded.get(name) {
+            Some(text) => Some(text.as_str()),
+            None => embedded(name),
+        }
+    }
+
+    pub fn text(&self, name: &str) -> String {                              // Igual que get para los archivos de FILES, que siempre existen
+        String::from(self.get(name).unwrap_or(""))
+    }
+
+    pub fn insert(&mut self, name: &str, text: String) -> Vec<FileError> {  // Acepta un archivo si no tiene errores
+        let errors = if name == "levels.txt" {
+            validate::check_levels_with(name, &text, self)
+        } else {
+            validate::check_file(name, &text)
+        };
+        if errors.is_empty() {
+            self.loaded.insert(name.to_string(), text);
+        }
+        errors
+    }
+
+    pub fn load_dir(&mut self, dir: &Path) -> Vec<String> {                 // Carga los archivos de un directorio, regresa los errores encontrados
+        let mut messages = Vec::new();
+        let levels = match read(dir, "levels.txt") {
+            Ok(text) => text,
+            Err(err) => {
+                messages.push(err);
+                None
+            },
+        };
+        let mut names: Vec<String> = FILES.iter().filter(|n| **n != "levels.txt").map(|n| n.to_string()).collect();
+        if let Some(text) = levels.as_ref() {
+            names.extend(referenced_maps(text));
+        }
+
+        for name in names.iter() {
+            match read(dir, name) {
+                Ok(Some(text)) => messages.extend(self.insert(name, text).iter().map(|e| e.to_string())),
+                Ok(None) => (),
+                Err(err) => messages.push(err),
+            }
+        }
+        if let Some(text) = levels {
+            messages.extend(self.insert("levels.txt", text).iter().map(|e| e.to_string()));
+        }
+        messages
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    dir: PathBuf,
+    stamps: Vec<(PathBuf, SystemTime)>,
+}
+
+impl Watcher {
+    pub fn new(dir: &Path) -> Watcher {
+        Watcher {
+            dir: dir.to_path_buf(),
+            stamps: stamps(dir),
+        }
+    }
+
+    pub fn changed(&mut self) -> bool {                                     // Verdadero si un archivo cambió, se creó o se borró desde la última revisión
+        let stamps = stamps(&self.dir);
+        if stamps == self.stamps {
+            return false;
+        }
+        self.stamps = stamps;
+        true
+    }
+}
+
+fn stamps(dir: &Path) -> Vec<(PathBuf, SystemTime)> {                      // Archivos .txt del directorio con su fecha de modificación
+    let mut stamps: Vec<(PathBuf, SystemTime)> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map(|e| e == "txt").unwrap_or(false))
+            .filter_map(|p| fs::metadata(&p).and_then(|m| m.modified()).ok().map(|t| (p, t)))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    stamps.sort();
+    stamps
+}
+
+fn read(dir: &Path, name: &str) -> Result<Option<String>, String> {         // Un archivo que no existe no es un error, se usa el incluido
+    let path = dir.join(name);
+    match fs::read_to_string(&path) {
+        Ok(text) => Ok(Some(text)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
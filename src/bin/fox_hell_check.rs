@@ -0,0 +1,74 @@
+/*
+ * Revisión de los archivos del juego. Sin argumentos revisa todos los archivos .txt de src/files (mapas, sprites
+ * y niveles); también se pueden indicar archivos o directorios. Cada error se imprime con el formato
+ * archivo:línea:columna: razón y el programa termina con código 1 si se encontró alguno, por lo que se puede
+ * usar antes de publicar un mapa nuevo o en la integración continua. Los niveles pueden usar los mapas de los
+ * directorios que se revisan además de los incluidos en el binario.
+ *
+ * Uso: fox_hell_check [ARCHIVO|DIRECTORIO]...
+ */
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use fox_hell_core::assets::Assets;
+use fox_hell_core::validate;
+
+fn main() {
+    let mut paths: Vec<PathBuf> = env::args().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        paths.push(Path::new(env!("CARGO_MANIFEST_DIR")).join("src").join("files"));
+    }
+
+    let mut files = Vec::new();
+    let mut assets = Assets::embedded();
+    for path in paths.iter() {
+        if path.is_dir() {
+            assets.load_dir(path);                                          // Los errores de cada archivo se muestran abajo
+        }
+        match collect(path, &mut files) {
+            Ok(()) => (),
+            Err(err) => {
+                eprintln!("No se pudo leer {}: {}", path.display(), err);
+                process::exit(2);
+            }
+        }
+    }
+
+    let mut count = 0;
+    for file in files.iter() {
+        let name = file.display().to_string();
+        let errors = match fs::read_to_string(file) {
+            Ok(text) => validate::check_file_with(&name, &text, &assets),
+            Err(err) => {
+                eprintln!("No se pudo leer {}: {}", name, err);
+                process::exit(2);
+            }
+        };
+        for err in errors.iter() {
+            println!("{}", err);
+        }
+        count += errors.len();
+    }
+
+    println!("{} archivos revisados, {} errores", files.len(), count);
+    if count > 0 {
+        process::exit(1);
+    }
+}
+
+fn collect(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {  // Archivos .txt de un directorio, en orden alfabético
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().map(|e| e == "txt").unwrap_or(false))
+        .collect();
+    entries.sort();
+    files.extend(entries);
+    Ok(())
+}
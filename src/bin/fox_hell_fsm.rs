@@ -0,0 +1,82 @@
+/*
+ * Exportación de la máquina de estados del jugador (ver fsm.rs) como Graphviz DOT o como JFLAP, para ver el
+ * diagrama sin mantener una imagen a mano:
+ *   cargo run --bin fox_hell_fsm | dot -Tpng -o StateMachine_FoxHell.png
+ *
+ * Con --replay se reproduce la partida grabada (ver replay.rs) sin dibujarla y cada transición se anota con
+ * el número de veces que se usó, sumando todas las repeticiones que se indiquen; así se ve qué transiciones
+ * usan realmente los jugadores. Para contar las de una partida propia basta con grabarla con
+ * fox_hell_term --record. Si una repetición no llega al resultado que se grabó (por ejemplo porque cambiaron
+ * las reglas del juego) se muestra un aviso, ya que sus cuentas no corresponden a la partida original.
+ *
+ * Uso: fox_hell_fsm [--format dot|jff] [--replay ARCHIVO]... [--output ARCHIVO]
+ */
+
+use std::env;
+use std::fs;
+use std::process;
+
+use fox_hell_core::fsm::StateMachine;
+use fox_hell_core::replay::{Replay, ReplayPlayer};
+
+fn main() {
+    let mut format = String::from("dot");
+    let mut replays: Vec<String> = Vec::new();
+    let mut output: Option<String> = None;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--format", Some(value)) if value == "dot" || value == "jff" => format = value,
+            ("--replay", Some(value)) => replays.push(value),
+            ("--output", Some(value)) => output = Some(value),
+            _ => usage(),
+        }
+    }
+
+    let machine = StateMachine::embedded();
+    let mut hits: Option<Vec<u32>> = None;
+    for path in replays.iter() {
+        let replay = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| Replay::from_text(&text)) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("No se pudo leer {}: {}", path, err);
+                process::exit(2);
+            }
+        };
+        let mut player = match ReplayPlayer::new(&replay) {
+            Ok(player) => player,
+            Err(err) => {
+                eprintln!("No se pudo reproducir {}: {}", path, err);
+                process::exit(2);
+            }
+        };
+        while player.step() {}
+        if player.result() != replay.result() {
+            eprintln!("Aviso: {} no llegó al resultado que se grabó", path);
+        }
+        let total = hits.get_or_insert_with(|| vec![0; machine.transitions.len()]);
+        for (count, hit) in total.iter_mut().zip(player.character().transition_hits().iter()) {
+            *count += hit;
+        }
+    }
+
+    let text = match format.as_str() {
+        "jff" => machine.to_jff(hits.as_deref()),
+        _ => machine.to_dot(hits.as_deref()),
+    };
+    match output {
+        Some(path) => {
+            if let Err(err) = fs::write(&path, text) {
+                eprintln!("No se pudo guardar {}: {}", path, err);
+                process::exit(2);
+            }
+        },
+        None => print!("{}", text),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("Uso: fox_hell_fsm [--format dot|jff] [--replay ARCHIVO]... [--output ARCHIVO]");
+    process::exit(2);
+}
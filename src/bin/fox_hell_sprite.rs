@@ -0,0 +1,192 @@
+/*
+ * Importación y exportación de sprites como imágenes PNG (ver spritesheet.rs), para dibujarlos con un editor
+ * de pixeles normal.
+ *
+ * import convierte una imagen en una línea de sprite y la imprime, lista para agregarla a fox_sprites.txt o a
+ * la sección SpriteStart de un mapa. Con --grid la imagen es una hoja de sprites dividida en celdas de
+ * ANCHOxALTO pixeles; las celdas vacías se saltan y las demás se nombran NOMBRE1, NOMBRE2... o con los
+ * nombres separados por comas que se indiquen. --colors fija los primeros colores (separados por guiones),
+ * --props agrega las propiedades de la celda (ver tile.rs) y --scale lee una imagen exportada con esa escala
+ * (--grid se indica en pixeles del sprite).
+ *
+ * export lee un archivo de sprites o un mapa y guarda cada sprite (o sólo los indicados) como NOMBRE.png en el
+ * directorio de --dir, o todos en una sola hoja con --sheet. Cada celda del mapa de bits mide --scale pixeles.
+ *
+ * Uso: fox_hell_sprite import IMAGEN.png NOMBRE[,NOMBRE...] [--grid ANCHOxALTO] [--scale N] [--colors COLORES]
+ *                      [--props PROPIEDADES]
+ *      fox_hell_sprite export ARCHIVO [--scale N] [--dir DIRECTORIO] [--sheet IMAGEN.png] [NOMBRE]...
+ */
+
+use std::env;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::Path;
+use std::process;
+
+use fox_hell_core::spritesheet::{self, Image};
+use fox_hell_core::sprite::{Palettes, Sprite};
+use fox_hell_core::validate;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.first().map(|a| a.as_str()) {
+        Some("import") => import(&args[1..]),
+        Some("export") => export(&args[1..]),
+        _ => usage(),
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn import(args: &[String]) -> Result<(), String> {
+    let (positional, options) = split_args(args, &["--grid", "--scale", "--colors", "--props"]);
+    let (path, name) = match positional.as_slice() {
+        [path, name] => (path, name),
+        _ => usage(),
+    };
+    let file = File::open(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+    let mut image = Image::decode(file).map_err(|e| format!("{}: {}", path, e))?;
+    if let Some(value) = option(&options, "--scale") {
+        image = image.downscale(parse_scale(value)?);
+    }
+    let colors: Vec<String> = match option(&options, "--colors") {
+        Some(list) => list.split('-').map(String::from).collect(),
+        None => Vec::new(),
+    };
+
+    let images = match option(&options, "--grid") {
+        Some(grid) => {
+            let (width, height) = parse_size(grid).ok_or_else(|| format!("tamaño de celda inválido '{}'", grid))?;
+            spritesheet::cells(&image, width, height).into_iter().filter(|cell| !cell.is_empty()).collect()
+        },
+        None => vec![image],
+    };
+    let names: Vec<String> = match name.contains(',') {
+        true => name.split(',').map(String::from).collect(),
+        false if images.len() == 1 => vec![name.to_string()],
+        false => (1..=images.len()).map(|i| format!("{}{}", name, i)).collect(),
+    };
+    if names.len() != images.len() {
+        return Err(format!("hay {} sprites y {} nombres", images.len(), names.len()));
+    }
+
+    for (image, name) in images.iter().zip(names.iter()) {
+        let spte = spritesheet::import(image, &colors).map_err(|e| format!("{}: {}", name, e))?;
+        let mut line = spte.to_line(name);
+        if let Some(props) = option(&options, "--props") {
+            line = format!("{}:{}", line, props);
+        }
+        if let Err((column, reason)) = validate::check_sprite_line(&line) {
+            return Err(format!("{}:{}: {}", name, column, reason));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn export(args: &[String]) -> Result<(), String> {
+    let (positional, options) = split_args(args, &["--scale", "--dir", "--sheet"]);
+    let path = positional.first().unwrap_or_else(|| usage());
+    let text = fs::read_to_string(path).map_err(|e| format!("No se pudo leer {}: {}", path, e))?;
+    let errors = validate::check_file(path, &text);
+    if let Some(err) = errors.first() {
+        return Err(err.to_string());
+    }
+    let scale = match option(&options, "--scale") {
+        Some(value) => parse_scale(value)?,
+        None => 1,
+    };
+
+    let mut sprites = read_sprites(&text).map_err(|(line, column, reason)| format!("{}:{}:{}: {}", path, line, column, reason))?;
+    if positional.len() > 1 {
+        let wanted: Vec<&String> = positional[1..].to_vec();
+        if let Some(name) = wanted.iter().find(|name| !sprites.iter().any(|(n, _)| n == **name)) {
+            return Err(format!("{} no tiene el sprite {}", path, name));
+        }
+        sprites.retain(|(name, _)| wanted.contains(&name));
+    }
+
+    if let Some(sheet) = option(&options, "--sheet") {
+        let list: Vec<&Sprite> = sprites.iter().map(|(_, spte)| spte).collect();
+        save(&spritesheet::sheet(&list, scale), Path::new(sheet))?;
+        println!("{} sprites guardados en {}", list.len(), sheet);
+        return Ok(());
+    }
+    let dir = Path::new(option(&options, "--dir").map(|d| d.as_str()).unwrap_or("."));
+    for (name, spte) in sprites.iter() {
+        save(&spritesheet::export(spte, scale), &dir.join(format!("{}.png", name)))?;
+    }
+    println!("{} sprites guardados en {}", sprites.len(), dir.display());
+    Ok(())
+}
+
+fn read_sprites(text: &str) -> Result<Vec<(String, Sprite)>, (usize, usize, String)> {                      // Sprites de un archivo de sprites o de la sección SpriteStart de un mapa
+    let in_map = text.lines().any(|line| line == "SpriteStart");
+    let mut inside = !in_map;
+    let mut palettes = Palettes::new();
+    let mut sprites = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        match line {
+            "SpriteStart" => inside = true,
+            "SpriteEnd" | "" if in_map => inside = false,
+            _ if !inside || line.is_empty() => (),
+            _ => match Sprite::parse_palette(line) {
+                Some((name, colors)) => { palettes.insert(name, colors); },
+                None => {
+                    let name = line.split(':').next().unwrap_or("").to_string();
+                    let spte = Sprite::from_line_with(line.to_string(), &palettes).map_err(|(column, reason)| (i + 1, column, reason))?;
+                    sprites.push((name, spte));
+                },
+            },
+        }
+    }
+    Ok(sprites)
+}
+
+fn save(image: &Image, path: &Path) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("No se pudo guardar {}: {}", path.display(), e))?;
+    image.encode(BufWriter::new(file)).map_err(|e| format!("No se pudo guardar {}: {}", path.display(), e))
+}
+
+fn split_args<'a>(args: &'a [String], names: &[&str]) -> (Vec<&'a String>, Vec<(&'a str, &'a String)>) {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if names.contains(&arg.as_str()) {
+            match iter.next() {
+                Some(value) => options.push((arg.as_str(), value)),
+                None => usage(),
+            }
+        } else if arg.starts_with("--") {
+            usage();
+        } else {
+            positional.push(arg);
+        }
+    }
+    (positional, options)
+}
+
+fn option<'a>(options: &[(&str, &'a String)], name: &str) -> Option<&'a String> {
+    options.iter().rev().find(|(n, _)| *n == name).map(|(_, value)| *value)
+}
+
+fn parse_scale(text: &str) -> Result<u32, String> {
+    text.parse().ok().filter(|s| *s > 0).ok_or_else(|| format!("escala inválida '{}'", text))
+}
+
+fn parse_size(text: &str) -> Option<(u32, u32)> {
+    let mut parts = text.splitn(2, 'x');
+    let width = parts.next()?.parse().ok().filter(|w| *w > 0)?;
+    let height = parts.next()?.parse().ok().filter(|h| *h > 0)?;
+    Some((width, height))
+}
+
+fn usage() -> ! {
+    eprintln!("Uso: fox_hell_sprite import IMAGEN.png NOMBRE[,NOMBRE...] [--grid ANCHOxALTO] [--scale N] [--colors COLORES]");
+    eprintln!("                      [--props PROPIEDADES]");
+    eprintln!("     fox_hell_sprite export ARCHIVO [--scale N] [--dir DIRECTORIO] [--sheet IMAGEN.png] [NOMBRE]...");
+    process::exit(2);
+}
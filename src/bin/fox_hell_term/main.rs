@@ -0,0 +1,55 @@
+/*
+ * Interfaz del juego para una terminal de Linux (por ejemplo a través de SSH). Se usa el mismo núcleo que
+ * la interfaz web: cada 45 ms se dibuja el juego, se ejecuta un update del jugador y, si hubo un cambio de
+ * estado, se actualiza el status. El dibujo se hace con el Renderer Terminal (medios bloques con colores de
+ * 24 bits): cada celda del mapa ocupa 8 columnas y 4 filas, por lo que con el mapa por defecto (20x10) la
+ * terminal debe tener al menos 160 columnas y 41 filas.
+ *
+ * El teclado se lee de stdin en modo raw desde un hilo aparte. Las flechas mueven al zorro, la barra
+ * espaciadora inicia el juego, 'p' pausa o reanuda, 't' cambia el tema de colores en la pantalla de inicio y
+ * 'q' o Ctrl-C terminan la ejecución. Una terminal no informa cuándo se libera una tecla, sólo repite la
+ * pulsación mientras se mantiene presionada, por lo que una tecla se considera liberada cuando deja de
+ * repetirse durante release_ms milisegundos (el valor por defecto cubre el retardo inicial de repetición de
+ * la mayoría de los sistemas).
+ *
+ * Con --record se guarda la partida en un archivo de repetición al salir, y con --replay se reproduce una
+ * repetición cuadro por cuadro comparando al final el puntaje y el tick de fin de partida con los grabados;
+ * agregando --headless la reproducción se hace sin dibujar, útil para comprobar repeticiones en pruebas.
+ *
+ * La tecla 's' guarda una fotografía de la partida en el archivo indicado con --save (fox_hell.snapshot por
+ * defecto) y con --load se reanuda una partida guardada. Una partida reanudada no se puede grabar, ya que las
+ * repeticiones siempre empiezan desde el inicio.
+ *
+ * Con --map se juega en otro mapa de juego con el formato de src/files/map0.txt; el tamaño del juego es el
+ * del mapa. Con --endless se juega el modo sin fin en arenas generadas a partir de la semilla, empezando en la
+ * dificultad indicada (0 es la más fácil). La fotografía guarda el mapa o las arenas de la partida, así que
+ * --load reanuda también estas partidas sin indicar otra vez --map ni --endless. Las repeticiones también
+ * guardan el mapa o la dificultad, y --replay arma la misma partida con la ruta del mapa que se grabó.
+ *
+ * Con --assets se leen los sprites, mapas y niveles de un directorio (por ejemplo src/files) en lugar de usar
+ * los incluidos en el binario, para probar cambios sin recompilar. Los archivos que no existen o que tienen
+ * errores se toman del binario; los errores se muestran antes de empezar. Durante el juego se revisa cerca de
+ * una vez por segundo si cambió algún archivo del directorio y, si es así, se recargan los mapas y los sprites
+ * sin reiniciar la partida (Character::reload). Al grabar con --record no se recarga, ya que la repetición
+ * guarda el directorio y vuelve a leer los archivos tal como estén al reproducirla.
+ *
+ * Uso: fox_hell_term [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]
+ *      fox_hell_term --assets DIRECTORIO [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO] [--load ARCHIVO]
+ *      fox_hell_term --map ARCHIVO [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]
+ *      fox_hell_term --endless DIFICULTAD [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]
+ *      fox_hell_term --load ARCHIVO [--release-ms N] [--save ARCHIVO]
+ *      fox_hell_term --replay ARCHIVO [--headless]
+ */
+
+#[cfg(unix)]
+mod tty;                                                                    // Módulo local con el modo raw de la terminal y el loop del juego
+
+#[cfg(unix)]
+fn main() {
+    tty::run();
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("La interfaz de terminal sólo está disponible en sistemas Unix");
+}
@@ -0,0 +1,485 @@
+/*
+ * Lectura del teclado en modo raw y loop del juego en la terminal.
+ */
+
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use fox_hell_core::arena::ArenaParams;
+use fox_hell_core::assets::{Assets, Watcher};
+use fox_hell_core::campaign::Campaign;
+use fox_hell_core::character::Character;
+use fox_hell_core::gamestate::GameState;
+use fox_hell_core::input::Input;
+use fox_hell_core::random::XorShift;
+use fox_hell_core::renderer::Renderer;
+use fox_hell_core::replay::{Recorder, Replay, ReplayPlayer, ReplayResult};
+use fox_hell_core::snapshot::Snapshot;
+use fox_hell_core::terminal::Terminal;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Key {
+    Left,
+    Right,
+    Up,
+    Down,
+    Space,
+    Pause,
+    Save,
+    Theme,
+    Quit,
+}
+
+struct Options {
+    seed: u64,
+    release: Duration,
+    record: Option<String>,
+    replay: Option<String>,
+    headless: bool,
+    load: Option<String>,
+    save: String,
+    map: Option<String>,
+    endless: Option<u32>,
+    assets: Option<String>,
+}
+
+#[derive(Default)]
+struct HeldKeys {                                                           // Última vez que se recibió cada tecla que se puede mantener presionada
+    left: Option<Instant>,
+    right: Option<Instant>,
+    down: Option<Instant>,
+    up: Option<Instant>,
+}
+
+pub fn run() {
+    let options = parse_args();
+
+    if let Some(path) = options.replay.as_ref() {
+        let replay = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|t| Replay::from_text(&t)) {
+            Ok(replay) => replay,
+            Err(err) => {
+                eprintln!("No se pudo leer la repetición {}: {}", path, err);
+                process::exit(1);
+            }
+        };
+        let result = if options.headless {
+            replay.run()
+        } else {
+            ReplayPlayer::new(&replay).map(|player| with_raw_mode(|| play_replay(player)))
+        };
+        match result {
+            Ok(result) => report(&replay.result(), &result),
+            Err(err) => {
+                eprintln!("No se pudo reproducir la repetición {}: {}", path, err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut assets = Assets::embedded();
+    if let Some(dir) = options.assets.as_ref() {                            // Los archivos con errores se reemplazan por los incluidos en el binario
+        let messages = assets.load_dir(Path::new(dir));
+        for message in messages.iter() {
+            eprintln!("{}", message);
+        }
+        if !messages.is_empty() {
+            eprintln!("Se usarán los archivos incluidos en lugar de los que tienen errores");
+            thread::sleep(Duration::from_secs(3));
+        }
+    }
+    let random = Box::new(XorShift::new(options.seed));
+
+    let character = match options.load.as_ref() {
+        Some(path) => match fs::read_to_string(path).map_err(|e| e.to_string())
+            .and_then(|t| Snapshot::from_text(&t))
+            .and_then(|s| Character::from_snapshot_with(&s, assets)) {
+            Ok(character) => character,
+            Err(err) => {
+                eprintln!("No se pudo cargar la partida {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => match options.map.as_ref() {
+            Some(path) => match Campaign::from_file(path) {
+                Ok(campaign) => Character::with_assets(campaign, assets, random),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            },
+            None => match options.endless {
+                Some(difficulty) => {
+                    let params = ArenaParams::default().with_difficulty(difficulty);
+                    let campaign = Campaign::endless(options.seed, &params, &assets);
+                    Character::with_assets(campaign, assets, random)
+                },
+                None => {
+                    let campaign = Campaign::from_assets(&assets).unwrap_or_else(|_| Campaign::embedded());
+                    Character::with_assets(campaign, assets, random)
+                },
+            },
+        },
+    };
+
+    let (character, recorder) = with_raw_mode(|| play(&options, character));
+    if let Some(path) = options.record.as_ref() {
+        let replay = recorder.finish(&character);
+        if let Err(err) = fs::write(path, replay.to_text()) {
+            eprintln!("No se pudo guardar la repetición {}: {}", path, err);
+            process::exit(1);
+        }
+        println!("Repetición guardada en {} (semilla {}, {} eventos)", path, replay.seed, replay.events.len());
+    }
+}
+
+fn play(options: &Options, mut character: Character) -> (Character, Recorder) { // Loop del juego controlado por el teclado
+    let keys = spawn_reader();
+    let mut recorder = Recorder::new(options.seed, &character, options.assets.clone());
+    let mut term = Terminal::new(character.view_width(), character.height());
+    let mut held = HeldKeys::default();
+    let mut watcher = options.assets.as_ref()
+        .filter(|_| options.record.is_none())                               // Al grabar no se recarga, la repetición usa los archivos del inicio
+        .map(|dir| Watcher::new(Path::new(dir)));
+    let mut notice: Option<(String, u32)> = None;                           // Mensaje de la última recarga y cuadros que le quedan en pantalla
+    let mut poll = 0;                                                       // Cuadros desde la última revisión de los archivos
+
+    'game: loop {
+        let frame_start = Instant::now();
+
+        if let (Some(watcher), Some(dir)) = (watcher.as_mut(), options.assets.as_ref()) {
+            poll += 1;
+            if poll == 22 {                                                 // Cerca de una vez por segundo se revisa si cambiaron los archivos
+                poll = 0;
+                if watcher.changed() {
+                    let mut assets = Assets::embedded();
+                    let messages = assets.load_dir(Path::new(dir));
+                    let text = match character.reload(assets) {
+                        Ok(()) if messages.is_empty() => String::from("Archivos recargados"),
+                        Ok(()) => messages[0].clone(),
+                        Err(err) => format!("No se recargaron los archivos: {}", err),
+                    };
+                    notice = Some((text, 66));
+                }
+            }
+        }
+
+        while let Ok(key) = keys.try_recv() {
+            if key == Key::Quit {
+                break 'game;
+            }
+            if key == Key::Save {                                           // Se guarda la partida sin detener el juego
+                let _ = fs::write(&options.save, character.snapshot().to_text());
+                continue;
+            }
+            if key == Key::Theme {                                          // El tema sólo cambia los colores, no se graba en la repetición
+                character.next_theme();
+                continue;
+            }
+            press(&mut character, &mut recorder, &mut held, key, frame_start);
+        }
+        release_stale(&mut character, &mut recorder, &mut held, frame_start, options.release);
+
+        character.draw(&mut term);                                          // Cada iteración se imprime el juego en la terminal
+        if let Some((text, frames)) = notice.as_mut() {
+            term.draw_text(0.0, 0.0, text, "yellow");
+            *frames -= 1;
+            if *frames == 0 {
+                notice = None;
+            }
+        }
+        character.update();                                                 // Se ejecuta un update en el jugador
+        if character.graph_changed() {                                      // Y si hubo un cambio de estado se actualiza el status
+            character.draw_graph(&mut term);
+        }
+
+        if !present(&term, frame_start) {
+            break;
+        }
+    }
+
+    (character, recorder)
+}
+
+fn play_replay(mut player: ReplayPlayer) -> ReplayResult {                  // Loop del juego controlado por una repetición, sólo se atiende 'q'
+    let keys = spawn_reader();
+    let mut term = Terminal::new(player.character().view_width(), player.character().height());
+
+    while !player.finished() {
+        let frame_start = Instant::now();
+        if keys.try_iter().any(|key| key == Key::Quit) {
+            break;
+        }
+
+        player.character().draw(&mut term);
+        player.step();
+        if player.character().graph_changed() {
+            player.character().draw_graph(&mut term);
+        }
+
+        if !present(&term, frame_start) {
+            break;
+        }
+    }
+
+    player.result()
+}
+
+fn present(term: &Terminal, frame_start: Instant) -> bool {                // Escribe el cuadro y espera el resto de los 45 ms
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if out.write_all(term.render().as_bytes()).and_then(|_| out.flush()).is_err() {
+        return false;
+    }
+
+    let elapsed = frame_start.elapsed();
+    if elapsed < Duration::from_millis(45) {
+        thread::sleep(Duration::from_millis(45) - elapsed);
+    }
+    true
+}
+
+fn report(expected: &ReplayResult, result: &ReplayResult) {                // Compara el resultado de la reproducción con el grabado
+    println!("Grabado:    tick final {}, puntaje {}, fin de partida {:?}", expected.end_tick, expected.score, expected.game_over_tick);
+    println!("Reproducido: tick final {}, puntaje {}, fin de partida {:?}", result.end_tick, result.score, result.game_over_tick);
+    if expected != result {
+        eprintln!("La reproducción no coincide con la grabación");
+        process::exit(1);
+    }
+}
+
+fn press(character: &mut Character, recorder: &mut Recorder, held: &mut HeldKeys, key: Key, now: Instant) {
+    if key == Key::Space {                                                  // En la web el juego inicia al liberar la barra espaciadora
+        recorder.apply(character, Input::Start);
+        return;
+    }
+    if key == Key::Pause {
+        recorder.apply(character, Input::Pause);
+        return;
+    }
+    if character.game_state != GameState::Play && character.game_state != GameState::Paused {
+        return;                                                             // En pausa las flechas sólo se registran
+    }
+    match key {
+        Key::Left => {
+            if held.right.take().is_some() {                                // Sólo se repite la última tecla, así que la contraria se libera
+                recorder.apply(character, Input::RightUp);
+            }
+            if held.left.is_none() {
+                recorder.apply(character, Input::LeftDown);
+            }
+            held.left = Some(now);
+        },
+        Key::Right => {
+            if held.left.take().is_some() {
+                recorder.apply(character, Input::LeftUp);
+            }
+            if held.right.is_none() {
+                recorder.apply(character, Input::RightDown);
+            }
+            held.right = Some(now);
+        },
+        Key::Down => {
+            if held.down.is_none() {
+                recorder.apply(character, Input::DownDown);
+            }
+            held.down = Some(now);
+        },
+        Key::Up => {
+            recorder.apply(character, Input::UpDown);                       // Cada repetición cuenta, así se vuelve a saltar al aterrizar
+            held.up = Some(now);
+        },
+        _ => {},
+    }
+}
+
+fn release_stale(character: &mut Character, recorder: &mut Recorder, held: &mut HeldKeys, now: Instant, release: Duration) {
+    let stale = |last: &Option<Instant>| last.is_some_and(|t| now.duration_since(t) >= release);
+    if stale(&held.left) {
+        held.left = None;
+        recorder.apply(character, Input::LeftUp);
+    }
+    if stale(&held.right) {
+        held.right = None;
+        recorder.apply(character, Input::RightUp);
+    }
+    if stale(&held.down) {
+        held.down = None;
+        recorder.apply(character, Input::DownUp);
+    }
+    if stale(&held.up) {                                                    // Soltar ARRIBA recorta el salto
+        held.up = None;
+        recorder.apply(character, Input::UpUp);
+    }
+}
+
+fn with_raw_mode<T, F: FnOnce() -> T>(f: F) -> T {                         // Ejecuta f con la terminal en modo raw y la restaura al terminar
+    let raw = match RawMode::enable() {
+        Ok(raw) => raw,
+        Err(err) => {
+            eprintln!("No se pudo poner la terminal en modo raw: {}", err);
+            process::exit(1);
+        }
+    };
+    let result = f();
+    drop(raw);
+    result
+}
+
+fn spawn_reader() -> Receiver<Key> {                                        // Hilo que lee stdin y traduce los bytes a teclas
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let stdin = io::stdin();
+        let mut stdin = stdin.lock();
+        let mut pending: Vec<u8> = Vec::new();
+        let mut buf = [0u8; 64];
+        loop {
+            let n = match stdin.read(&mut buf) {
+                Ok(0) | Err(_) => {
+                    let _ = tx.send(Key::Quit);
+                    return;
+                },
+                Ok(n) => n,
+            };
+            pending.extend_from_slice(&buf[..n]);
+            for key in parse_keys(&mut pending) {
+                if tx.send(key).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn parse_keys(pending: &mut Vec<u8>) -> Vec<Key> {                         // Reconoce las teclas completas y deja en pending las secuencias incompletas
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < pending.len() {
+        match pending[i] {
+            0x1b => {
+                if i + 2 >= pending.len() {
+                    break;
+                }
+                if pending[i + 1] == b'[' || pending[i + 1] == b'O' {
+                    match pending[i + 2] {
+                        b'A' => keys.push(Key::Up),
+                        b'B' => keys.push(Key::Down),
+                        b'C' => keys.push(Key::Right),
+                        b'D' => keys.push(Key::Left),
+                        _ => {},
+                    }
+                    i += 3;
+                } else {
+                    i += 1;
+                }
+            },
+            b' ' => { keys.push(Key::Space); i += 1; },
+            b'p' | b'P' => { keys.push(Key::Pause); i += 1; },
+            b's' | b'S' => { keys.push(Key::Save); i += 1; },
+            b't' | b'T' => { keys.push(Key::Theme); i += 1; },
+            b'q' | b'Q' | 0x03 => { keys.push(Key::Quit); i += 1; },
+            _ => i += 1,
+        }
+    }
+    pending.drain(..i);
+    keys
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        seed: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as u64)
+            .unwrap_or(0),
+        release: Duration::from_millis(600),
+        record: None,
+        replay: None,
+        headless: false,
+        load: None,
+        save: String::from("fox_hell.snapshot"),
+        map: None,
+        endless: None,
+        assets: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--headless" {
+            options.headless = true;
+            continue;
+        }
+        let value = args.next();
+        let number = value.as_ref().and_then(|v| v.parse::<u64>().ok());
+        match (arg.as_ref(), number, value) {
+            ("--seed", Some(seed), _) => options.seed = seed,
+            ("--release-ms", Some(ms), _) => options.release = Duration::from_millis(ms),
+            ("--record", _, Some(path)) => options.record = Some(path),
+            ("--replay", _, Some(path)) => options.replay = Some(path),
+            ("--load", _, Some(path)) => options.load = Some(path),
+            ("--save", _, Some(path)) => options.save = path,
+            ("--map", _, Some(path)) => options.map = Some(path),
+            ("--assets", _, Some(path)) => options.assets = Some(path),
+            ("--endless", _, Some(difficulty)) => {                        // Una dificultad que no cabe en u32 es un error, no se recorta
+                options.endless = Some(difficulty.parse::<u32>().unwrap_or_else(|_| usage()));
+            },
+            _ => usage(),
+        }
+    }
+    if options.headless && options.replay.is_none() {
+        usage();
+    }
+    if options.load.is_some() && options.record.is_some() {                // Una repetición siempre empieza desde una partida nueva
+        usage();
+    }
+    let custom = options.map.is_some() as u8 + options.endless.is_some() as u8;
+    if custom > 1 || (custom > 0 && options.load.is_some()) {
+        usage();                                                            // La fotografía ya indica el mapa o el modo sin fin de la partida
+    }
+    options
+}
+
+fn usage() -> ! {
+    eprintln!("Uso: fox_hell_term [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]");
+    eprintln!("     fox_hell_term --assets DIRECTORIO [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO] [--load ARCHIVO]");
+    eprintln!("     fox_hell_term --map ARCHIVO [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]");
+    eprintln!("     fox_hell_term --endless DIFICULTAD [--seed N] [--release-ms N] [--record ARCHIVO] [--save ARCHIVO]");
+    eprintln!("     fox_hell_term --load ARCHIVO [--release-ms N] [--save ARCHIVO]");
+    eprintln!("     fox_hell_term --replay ARCHIVO [--headless]");
+    process::exit(2);
+}
+
+struct RawMode {                                                            // Mientras exista, la terminal está en modo raw y en la pantalla alterna
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<RawMode> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        let _ = io::stdout().flush();
+        Ok(RawMode { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
@@ -17,13 +17,29 @@
  * Esta clase permite tanto comprimir una matriz como descomprimirla.
  * 
  * Aquí también se definen métodos para invertir el lado del mapa de bits y así invertir un sprite de dirección
- * y también para hacer un giro hacia la derecha, estas operaciones se aplican sobre la matriz 8x8.
+ * y también para hacer un giro hacia la derecha, estas operaciones se aplican sobre la matriz completa.
+ *
+ * Un sprite puede medir más de 8x8 (por ejemplo 16x16, 8x16 o 32x32 para un jefe), por lo que la matriz es de
+ * tamaño variable: cada fila es un Vec y todas miden lo mismo. Los tres u64 sólo alcanzan para 8x8, así que
+ * los sprites de otro tamaño se escriben con una versión compacta: ANCHOxALTO.DATOS, donde DATOS es el índice
+ * de color de cada celda (3 bits, fila por fila y empezando por el bit más significativo) codificado en
+ * base64 sin relleno. Por ejemplo un sprite vacío de 16x8 es 16x8. seguido de 64 letras A. from_text acepta
+ * las dos versiones y to_text usa los tres u64 cuando el mapa de bits es de 8x8, así los archivos que ya
+ * existen se leen y se escriben igual que antes.
+ *
+ * Con 3 bits un sprite sólo puede usar 7 colores. Para usar más se agrega la profundidad al tamaño:
+ * ANCHOxALTOx4.DATOS (4 bits por celda, hasta 15 colores) o ANCHOxALTOx8.DATOS (8 bits, hasta 255 colores).
+ * to_text elige la menor profundidad que alcanza para los índices del mapa de bits.
  */
 
 use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
-pub struct BitMap( pub [[u8; 8]; 8] );
+const DEPTHS: [usize; 3] = [3, 4, 8];                                       // Bits por celda que se pueden usar en la versión compacta
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitMap( pub Vec<Vec<u8>> );
 
 impl fmt::Display for BitMap {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -32,25 +48,43 @@ impl fmt::Display for BitMap {
             for bit in line.iter() {
                 write!(f, "{}, ", bit)?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
         writeln!(f, "---")
     }
 }
 
+impl Default for BitMap {
+    fn default() -> Self {
+        BitMap::new()
+    }
+}
+
 impl BitMap {
     pub fn new() -> BitMap {
-        BitMap([[0; 8]; 8])
+        BitMap::with_size(8, 8)
+    }
+
+    pub fn with_size(width: usize, height: usize) -> BitMap {
+        BitMap(vec![vec![0; width]; height])
+    }
+
+    pub fn width(&self) -> usize {
+        self.0.first().map(|row| row.len()).unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.0.len()
     }
 
-    pub fn compress_map(self) -> (u64, u64, u64) {
+    pub fn compress_map(&self) -> (u64, u64, u64) {                         // Sólo para mapas de bits de 8x8
         let mut a:u64=0;
         let mut b:u64=0;
         let mut c:u64=0;
         let mut i=0;
 
-        for line in self.0.iter() {
-            for color in line.iter() {
+        for line in self.0.iter().take(8) {
+            for color in line.iter().take(8) {
                 a |= ((*color as u64&4)>>2)<<i;
                 b |= ((*color as u64&2)>>1)<<i;
                 c |= (*color as u64&1)<<i;
@@ -63,41 +97,172 @@ impl BitMap {
 
     pub fn from_compress(compressed: (u64, u64, u64)) -> BitMap {
         let (a, b, c) = compressed;
-        let mut map: [[u8; 8]; 8] = [[0; 8]; 8];
+        let mut map = BitMap::new();
         let mut i=0;
 
-        for line in 0..8 {
-            for bit in 0..8 {
-                let temp:u8 = ((((a&(1<<i))>>i)<<2) | (((b&(1<<i))>>i)<<1) | (c&(1<<i))>>i) as u8;
-                map[line][bit] = temp;
+        for line in map.0.iter_mut() {
+            for bit in line.iter_mut() {
+                *bit = ((((a&(1<<i))>>i)<<2) | (((b&(1<<i))>>i)<<1) | (c&(1<<i))>>i) as u8;
                 i+=1;
             }
         }
 
-        BitMap(map)
+        map
+    }
+
+    pub fn depth(&self) -> usize {                                          // Bits por celda necesarios para guardar los índices de color
+        match self.0.iter().flatten().max().copied().unwrap_or(0) {
+            0..=7 => 3,
+            8..=15 => 4,
+            _ => 8,
+        }
+    }
+
+    pub fn to_text(&self) -> String {                                       // Tres u64 si es de 8x8 con 3 bits, si no la versión compacta
+        let depth = self.depth();
+        if self.width() == 8 && self.height() == 8 && depth == 3 {
+            let (a, b, c) = self.compress_map();
+            return format!("{}-{}-{}", a, b, c);
+        }
+
+        let mut bits: Vec<bool> = Vec::new();
+        for color in self.0.iter().flatten() {
+            for shift in (0..depth).rev() {
+                bits.push(color >> shift & 1 == 1);
+            }
+        }
+        let mut text = match depth {
+            3 => format!("{}x{}.", self.width(), self.height()),
+            _ => format!("{}x{}x{}.", self.width(), self.height(), depth),
+        };
+        for chunk in bits.chunks(6) {
+            let value = chunk.iter().enumerate().fold(0, |acc, (i, bit)| acc | (*bit as usize) << (5 - i));
+            text.push(BASE64[value] as char);
+        }
+        text
+    }
+                                                                            // Lee cualquiera de las dos versiones, el error indica la posición en el texto y la razón
+    pub fn from_text(text: &str) -> Result<BitMap, (usize, String)> {
+        let (size, data) = match text.find('.') {
+            Some(dot) => (&text[..dot], &text[dot + 1..]),
+            None => {
+                let numbers: Vec<&str> = text.split('-').collect();
+                if numbers.len() != 3 {
+                    return Err((0, format!("el mapa de bits debe tener 3 números o ser ANCHOxALTO.DATOS, tiene {} números", numbers.len())));
+                }
+                let mut parts = [0u64; 3];
+                let mut offset = 0;
+                for (part, number) in parts.iter_mut().zip(numbers.iter()) {
+                    *part = number.parse().map_err(|_| (offset, format!("número u64 inválido '{}'", number)))?;
+                    offset += number.len() + 1;
+                }
+                return Ok(BitMap::from_compress((parts[0], parts[1], parts[2])));
+            },
+        };
+
+        let dims: Vec<&str> = size.split('x').collect();
+        let side = |n: &str| n.parse::<usize>().ok().filter(|n| *n > 0 && *n <= 256);
+        let (width, height) = match (dims.len(), side(dims[0]), dims.get(1).and_then(|n| side(n))) {
+            (2, Some(width), Some(height)) | (3, Some(width), Some(height)) => (width, height),
+            _ => return Err((0, format!("tamaño inválido '{}', se esperaba ANCHOxALTO[xBITS] (de 1 a 256)", size))),
+        };
+        let depth = match dims.get(2).map(|d| d.parse::<usize>()) {
+            None => 3,
+            Some(Ok(depth)) if DEPTHS.contains(&depth) => depth,
+            Some(_) => return Err((0, format!("profundidad inválida '{}', se puede usar 3, 4 u 8 bits", dims[2]))),
+        };
+        let chars = (width * height * depth).div_ceil(6);
+        if data.len() != chars {
+            return Err((size.len() + 1, format!("un mapa de bits de {}x{} tiene {} caracteres, hay {}", width, height, chars, data.len())));
+        }
+
+        let mut bits: Vec<bool> = Vec::with_capacity(chars * 6);
+        for (i, c) in data.bytes().enumerate() {
+            let value = match BASE64.iter().position(|b| *b == c) {
+                Some(value) => value,
+                None => return Err((size.len() + 1 + i, format!("carácter inválido '{}'", c as char))),
+            };
+            for shift in (0..6).rev() {
+                bits.push(value >> shift & 1 == 1);
+            }
+        }
+        let mut map = BitMap::with_size(width, height);
+        for (pixel, color) in map.0.iter_mut().flatten().zip(bits.chunks(depth)) {
+            *pixel = color.iter().fold(0, |acc, bit| acc << 1 | *bit as u8);
+        }
+        Ok(map)
     }
 
     pub fn invert_side(&mut self) {
-        for i in 0..8 {
-            for j in 0..4 {
-                let temp = self.0[i][j];
-                self.0[i][j] = self.0[i][(7-j) as usize];
-                self.0[i][(7-j) as usize] = temp;
+        for row in self.0.iter_mut() {
+            row.reverse();
+        }
+    }
+
+    pub fn right_rotate(&mut self) {                                        // Un mapa de bits de ANCHOxALTO queda de ALTOxANCHO
+        let (width, height) = (self.width(), self.height());
+        let mut temp = BitMap::with_size(height, width);
+        for i in 0..width {
+            for j in 0..height {
+                temp.0[i][j] = self.0[height-1-j][i];
             }
         }
+        *self = temp;
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    pub fn right_rotate(&mut self) {
-        let mut temp = BitMap::new();
-        for i in 0..8 {
-            for j in 0..8 {
-                temp.0[i][j] = self.0[7-j][i];
+    fn pattern(width: usize, height: usize) -> BitMap {                     // Mapa de bits con los índices 0 a 7 sin simetrías
+        let mut map = BitMap::with_size(width, height);
+        for (y, row) in map.0.iter_mut().enumerate() {
+            for (x, pixel) in row.iter_mut().enumerate() {
+                *pixel = ((x * 3 + y * 5 + x * y) % 8) as u8;
             }
         }
-        for i in 0..8 {
-            for j in 0..8 {
-                self.0[i][j] = temp.0[i][j];
+        map
+    }
+
+    #[test]
+    fn round_trips_every_size() {
+        let small = pattern(8, 8);
+        assert_eq!(small.to_text().split('-').count(), 3);                  // Los de 8x8 siguen con los tres u64
+        assert_eq!(BitMap::from_text(&small.to_text()), Ok(small));
+        for (width, height) in [(8, 16), (16, 8), (16, 16), (5, 3)] {
+            let map = pattern(width, height);
+            let text = map.to_text();
+            assert!(text.starts_with(&format!("{}x{}.", width, height)), "{}", text);
+            assert_eq!(text.len(), format!("{}x{}.", width, height).len() + (width * height * 3).div_ceil(6));
+            assert_eq!(BitMap::from_text(&text), Ok(map));
+        }
+        let empty = format!("16x8.{}", "A".repeat(64));
+        assert_eq!(BitMap::from_text(&empty), Ok(BitMap::with_size(16, 8)));
+        assert_eq!(BitMap::with_size(16, 8).to_text(), empty);
+    }
+
+    #[test]
+    fn rejects_bad_compact_text() {
+        assert!(BitMap::from_text("8x16.AAAA").is_err());
+        assert!(BitMap::from_text("0x8.").is_err());
+        assert!(BitMap::from_text(&format!("16x8.{}!", "A".repeat(63))).unwrap_err().1.contains("carácter inválido"));
+    }
+
+    #[test]
+    fn right_rotate_swaps_width_and_height() {
+        for (width, height) in [(8, 16), (16, 16)] {
+            let map = pattern(width, height);
+            let mut rotated = map.clone();
+            rotated.right_rotate();
+            assert_eq!((rotated.width(), rotated.height()), (height, width));
+            assert_eq!(rotated.0[0][height - 1], map.0[0][0]);              // La esquina superior izquierda queda arriba a la derecha
+            assert_eq!(rotated.0[width - 1][0], map.0[height - 1][width - 1]);
+            assert_eq!(BitMap::from_text(&rotated.to_text()), Ok(rotated.clone()));
+            for _ in 0..3 {
+                rotated.right_rotate();
             }
+            assert_eq!(rotated, map);
         }
     }
 }
@@ -0,0 +1,117 @@
+/*
+ * Cámara que indica qué parte de un nivel se ve en pantalla. Los niveles pueden ser más anchos que la
+ * pantalla (la ventana visible, o viewport), y la cámara sigue al zorro horizontalmente manteniéndolo al
+ * centro mientras no se llegue a un extremo del nivel.
+ *
+ * Si el mapa da la vuelta (wrap), al salir por un lado se aparece por el otro y la cámara también da la
+ * vuelta, por lo que puede mostrar el final y el inicio del nivel al mismo tiempo. Si no da la vuelta, la
+ * cámara se detiene en los bordes del nivel.
+ *
+ * Las coordenadas del mundo son las celdas del nivel y las de la pantalla son las celdas de la ventana visible.
+ */
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Camera {
+    pub x: u32,                                                             // Primera columna del nivel que se ve en pantalla
+    pub width: u32,                                                         // Ancho de la ventana visible
+    pub world_width: u32,                                                   // Ancho del nivel
+    pub wrap: bool,
+}
+
+impl Camera {
+    pub fn fixed(width: u32) -> Camera {                                    // Cámara de un mapa que cabe completo en pantalla
+        Camera {
+            x: 0,
+            width,
+            world_width: width,
+            wrap: false,
+        }
+    }
+
+    pub fn follow(target: u32, width: u32, world_width: u32, wrap: bool) -> Camera {
+        let width = width.min(world_width);
+        let x = if width == world_width {
+            0
+        } else if wrap {
+            (target + world_width - width / 2) % world_width
+        } else {
+            target.saturating_sub(width / 2).min(world_width - width)
+        };
+        Camera {
+            x,
+            width,
+            world_width,
+            wrap,
+        }
+    }
+
+    pub fn to_world(&self, x: u32) -> u32 {                                 // Columna del nivel que se ve en la columna x de la pantalla
+        if self.wrap {
+            (self.x + x) % self.world_width
+        } else {
+            self.x + x
+        }
+    }
+
+    pub fn to_screen(&self, x: i32) -> Option<u32> {                        // Columna de la pantalla en que se ve la columna x del nivel, si es visible
+        let dx = if self.wrap {
+            (x - self.x as i32).rem_euclid(self.world_width.max(1) as i32)
+        } else {
+            x - self.x as i32
+        };
+        if dx >= 0 && dx < self.width as i32 {
+            Some(dx as u32)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follow_stops_at_the_level_edges() {
+        assert_eq!(Camera::follow(25, 20, 60, false).x, 15);                // El zorro queda al centro
+        assert_eq!(Camera::follow(3, 20, 60, false).x, 0);
+        assert_eq!(Camera::follow(58, 20, 60, false).x, 40);
+        assert_eq!(Camera::follow(59, 20, 60, false).x, 40);
+        assert_eq!(Camera::follow(7, 20, 12, false), Camera::fixed(12));    // El nivel cabe completo en pantalla
+        assert_eq!(Camera::follow(7, 20, 20, true).x, 0);
+    }
+
+    #[test]
+    fn to_screen_hides_columns_outside_the_window() {
+        let left = Camera::follow(3, 20, 60, false);
+        assert_eq!(left.to_screen(0), Some(0));
+        assert_eq!(left.to_screen(19), Some(19));
+        assert_eq!(left.to_screen(20), None);
+        assert_eq!(left.to_screen(-1), None);
+
+        let right = Camera::follow(59, 20, 60, false);
+        assert_eq!(right.to_screen(39), None);
+        assert_eq!(right.to_screen(40), Some(0));
+        assert_eq!(right.to_screen(59), Some(19));
+        assert_eq!(right.to_screen(60), None);
+        for x in 0..20 {
+            assert_eq!(right.to_screen(right.to_world(x) as i32), Some(x));
+        }
+    }
+
+    #[test]
+    fn wrapping_camera_shows_both_ends() {
+        let camera = Camera::follow(2, 20, 60, true);
+        assert_eq!(camera.x, 52);
+        assert_eq!(camera.to_world(0), 52);
+        assert_eq!(camera.to_world(7), 59);
+        assert_eq!(camera.to_world(8), 0);
+        assert_eq!(camera.to_screen(2), Some(10));                          // El zorro sigue al centro
+        assert_eq!(camera.to_screen(55), Some(3));
+        assert_eq!(camera.to_screen(-1), Some(7));                          // -1 es la última columna del nivel
+        assert_eq!(camera.to_screen(30), None);
+        for x in 0..20 {
+            assert_eq!(camera.to_screen(camera.to_world(x) as i32), Some(x));
+        }
+    }
+}
@@ -0,0 +1,283 @@
+/*
+ * Lista de niveles que forman una partida. El archivo levels.txt se incluye en el binario igual que los mapas
+ * y sigue su estilo: una versión en la primera línea y una sección LevelStart/LevelEnd por nivel con
+ * propiedades Nombre:Valor:
+ *   FoxHellLevels 1
+ *   LevelStart
+ *   Name:Caverna
+ *   Map:map0.txt
+ *   Goal:10
+ *   IceRate:20
+ *   AppleRate:20
+ *   Duration:0
+ *   LevelEnd
+ *
+ * Goal es el número de manzanas que hay que recoger en el nivel para pasar al siguiente (0 para un nivel sin
+ * fin), IceRate y AppleRate indican cada cuántas iteraciones se dispara una roca de lava o una manzana, y
+ * Duration es el número de updates que dura el nivel antes de perder por tiempo (0 para no tener límite).
+ *
+ * Al recoger las manzanas que pide un nivel se muestra mapLevel.txt con el número del siguiente nivel durante
+ * unos segundos (o hasta presionar la barra espaciadora) y se continúa en el siguiente mapa conservando el
+ * puntaje y las vidas. Si el nivel tiene tiempo límite, el tiempo restante se muestra al centro del HUD y al
+ * agotarse se pierde la partida. Al completar el último nivel se muestra mapVictory.txt (GameState::Victory).
+ * Al volver a empezar después de perder o de ganar se regresa al primer nivel.
+ *
+ * Los mapas se buscan por nombre entre los archivos del juego (ver assets.rs). Todos los niveles
+ * deben tener el mismo alto y el mismo ancho visible, ya que la pantalla no cambia de tamaño durante la partida.
+ *
+ * En el modo sin fin (Campaign::endless) los niveles son arenas generadas (ver arena.rs): al alcanzar la meta
+ * se agrega una arena nueva un poco más difícil, y al volver a empezar se genera otra primera arena.
+ *
+ * Cada partida recuerda de dónde salieron sus niveles (CampaignSource) para que las fotografías y las
+ * repeticiones puedan volver a armarla con from_source. Se escribe en una línea de texto:
+ *   levels                     los niveles de levels.txt (los de assets o los incluidos en el binario)
+ *   map ARCHIVO                un solo mapa leído de un archivo (--map en la terminal)
+ *   endless DIFICULTAD SEMILLA...  el modo sin fin con la dificultad inicial y la semilla de cada arena
+ * Las arenas se vuelven a generar con los parámetros por defecto y la dificultad guardada.
+ */
+
+use std::fs;
+
+use crate::arena::{self, ArenaParams};
+use crate::assets::Assets;
+use crate::gamemap::GameMap;
+use crate::validate;
+
+pub const LEVELS_VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Level {
+    pub name: String,
+    pub map: GameMap,
+    pub file: Option<String>,
+    pub goal: u16,
+    pub ice_rate: usize,
+    pub apple_rate: usize,
+    pub duration: u64,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CampaignSource {
+    Levels,
+    Map(String),
+    Endless { difficulty: u32, seeds: Vec<u64> },
+}
+
+#[derive(Debug, Clone)]
+pub struct Campaign {
+    pub levels: Vec<Level>,
+    pub arena: Option<ArenaParams>,
+    pub source: CampaignSource,
+}
+
+impl CampaignSource {
+    pub fn to_text(&self) -> String {
+        match self {
+            CampaignSource::Levels => String::from("levels"),
+            CampaignSource::Map(path) => format!("map {}", path),
+            CampaignSource::Endless { difficulty, seeds } => {
+                let seeds: Vec<String> = seeds.iter().map(|seed| seed.to_string()).collect();
+                format!("endless {} {}", difficulty, seeds.join(" "))
+            },
+        }
+    }
+
+    pub fn parse(text: &str) -> Option<CampaignSource> {
+        let mut fields = text.trim().splitn(2, ' ');
+        match (fields.next(), fields.next().map(|rest| rest.trim())) {
+            (Some("levels"), None) => Some(CampaignSource::Levels),
+            (Some("map"), Some(path)) if !path.is_empty() => Some(CampaignSource::Map(path.to_string())),
+            (Some("endless"), Some(rest)) => {
+                let mut numbers = rest.split_whitespace();
+                let difficulty = numbers.next()?.parse().ok()?;
+                let seeds = numbers.map(|seed| seed.parse().ok()).collect::<Option<Vec<u64>>>()?;
+                if seeds.is_empty() {
+                    return None;
+                }
+                Some(CampaignSource::Endless { difficulty, seeds })
+            },
+            _ => None,
+        }
+    }
+}
+
+impl Level {
+    pub fn endless(map: GameMap) -> Level {                                 // Nivel sin meta ni límite de tiempo, como el juego original
+        Level {
+            name: String::new(),
+            map,
+            file: None,
+            goal: 0,
+            ice_rate: 20,
+            apple_rate: 20,
+            duration: 0,
+        }
+    }
+
+    pub fn arena(seed: u64, params: &ArenaParams, assets: &Assets) -> Level { // Nivel sin límite de tiempo en una arena generada
+        Level {
+            name: format!("Arena {}", params.difficulty + 1),
+            map: arena::generate(seed, params, assets),
+            file: None,
+            goal: params.goal(),
+            ice_rate: params.ice_rate(),
+            apple_rate: 20,
+            duration: 0,
+        }
+    }
+}
+
+impl Campaign {
+    pub fn embedded() -> Campaign {                                         // Niveles incluidos en el binario (files/levels.txt)
+        Campaign::from_assets(&Assets::embedded()).unwrap()
+    }
+
+    pub fn from_assets(assets: &Assets) -> Result<Campaign, String> {       // Niveles de levels.txt con los mapas cargados en assets
+        Campaign::from_text_with(&assets.text("levels.txt"), assets)
+    }
+
+    pub fn single(map: GameMap) -> Campaign {                               // Partida de un solo nivel sin fin en el mapa indicado
+        Campaign {
+            levels: vec![Level::endless(map)],
+            arena: None,
+            source: CampaignSource::Map(String::new()),
+        }
+    }
+
+    pub fn from_file(path: &str) -> Result<Campaign, String> {              // Partida de un solo nivel en un mapa leído de un archivo
+        let text = fs::read_to_string(path).map_err(|err| format!("no se pudo leer el mapa {}: {}", path, err))?;
+        let errors = validate::check_map_with(path, &text, &validate::level_hud());
+        if !errors.is_empty() {                                             // Todos los errores, uno por línea
+            return Err(errors.iter().map(|err| err.to_string()).collect::<Vec<String>>().join("\n"));
+        }
+        let map = GameMap::from_text(text).map_err(|(line, reason)| format!("{}:{}: {}", path, line, reason))?;
+        let mut campaign = Campaign::single(map);
+        campaign.source = CampaignSource::Map(path.to_string());
+        Ok(campaign)
+    }
+
+    pub fn endless(seed: u64, params: &ArenaParams, assets: &Assets) -> Campaign { // Partida sin fin en arenas generadas con los sprites de assets
+        let mut campaign = Campaign {
+            levels: Vec::new(),
+            arena: Some(*params),
+            source: CampaignSource::Endless { difficulty: params.difficulty, seeds: Vec::new() },
+        };
+        campaign.add_arena(seed, assets);
+        campaign
+    }
+
+    pub fn add_arena(&mut self, seed: u64, assets: &Assets) {               // Agrega una arena más difícil que la anterior
+        if let Some(params) = self.arena {
+            let params = params.with_difficulty(params.difficulty + self.levels.len() as u32);
+            self.levels.push(Level::arena(seed, &params, assets));
+            if let CampaignSource::Endless { seeds, .. } = &mut self.source {
+                seeds.push(seed);
+            }
+        }
+    }
+                                                                            // Vuelve a armar la partida de la que salió source
+    pub fn from_source(source: &CampaignSource, assets: &Assets) -> Result<Campaign, String> {
+        match source {
+            CampaignSource::Levels => Campaign::from_assets(assets),
+            CampaignSource::Map(path) => Campaign::from_file(path),
+            CampaignSource::Endless { difficulty, seeds } => {
+                let params = ArenaParams::default().with_difficulty(*difficulty);
+                let first = seeds.first().ok_or_else(|| String::from("modo sin fin sin semillas"))?;
+                let mut campaign = Campaign::endless(*first, &params, assets);
+                for seed in seeds.iter().skip(1) {
+                    campaign.add_arena(*seed, assets);
+                }
+                Ok(campaign)
+            },
+        }
+    }
+
+    pub fn from_text(text: &str) -> Result<Campaign, String> {
+        Campaign::from_text_with(text, &Assets::embedded())
+    }
+
+    pub fn from_text_with(text: &str, assets: &Assets) -> Result<Campaign, String> {
+        Campaign::parse_with(text, assets).map_err(|(line, reason)| match line {
+            0 => reason,
+            _ => format!("línea {}: {}", line, reason),
+        })
+    }
+                                                                            // Igual que from_text, pero el error indica por separado la línea (0 si es de todo el archivo)
+    pub fn parse(text: &str) -> Result<Campaign, (usize, String)> {
+        Campaign::parse_with(text, &Assets::embedded())
+    }
+
+    pub fn parse_with(text: &str, assets: &Assets) -> Result<Campaign, (usize, String)> {
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, header)) if header.trim() == format!("FoxHellLevels {}", LEVELS_VERSION) => {},
+            Some((_, header)) => return Err((1, format!("versión de niveles no soportada '{}'", header))),
+            None => return Err((0, String::from("lista de niveles vacía"))),
+        }
+
+        let mut levels = Vec::new();
+        let mut current: Option<(Level, bool)> = None;                      // Nivel en construcción y si ya tiene mapa
+        for (i, line) in lines {
+            let line = line.trim();
+            let fail = |reason: &str| (i + 1, format!("{} '{}'", reason, line));
+
+            if line.is_empty() {
+                continue;
+            }
+            match (line, current.take()) {
+                ("LevelStart", None) => current = Some((Level::endless(GameMap::new()), false)),
+                ("LevelEnd", Some((level, true))) => levels.push(level),
+                ("LevelEnd", Some(_)) => return Err(fail("nivel sin mapa")),
+                (_, None) => return Err(fail("línea fuera de un nivel")),
+                (_, Some((mut level, has_map))) => {
+                    let mut parts = line.splitn(2, ':');
+                    let (key, value) = match (parts.next(), parts.next()) {
+                        (Some(key), Some(value)) => (key, value.trim()),
+                        _ => return Err(fail("se esperaba Nombre:Valor")),
+                    };
+                    let ok = match key {
+                        "Name" => { level.name = value.to_string(); true },
+                        "Map" => match assets.get(value) {
+                            Some(text) => {
+                                level.map = GameMap::from_text(String::from(text)).map_err(|_| fail("mapa inválido"))?;
+                                level.file = Some(value.to_string());
+                                true
+                            },
+                            None => return Err(fail("mapa desconocido")),
+                        },
+                        "Goal" => value.parse().map(|v| level.goal = v).is_ok(),
+                        "IceRate" => value.parse().map(|v| level.ice_rate = v).is_ok() && level.ice_rate > 0,
+                        "AppleRate" => value.parse().map(|v| level.apple_rate = v).is_ok() && level.apple_rate > 0,
+                        "Duration" => value.parse().map(|v| level.duration = v).is_ok(),
+                        _ => return Err(fail("propiedad desconocida")),
+                    };
+                    if !ok {
+                        return Err(fail("valor inválido"));
+                    }
+                    current = Some((level, has_map || key == "Map"));
+                },
+            }
+        }
+        if current.is_some() {
+            return Err((0, String::from("falta LevelEnd al final del archivo")));
+        }
+
+        let campaign = Campaign { levels, arena: None, source: CampaignSource::Levels };
+        campaign.check_sizes().map_err(|reason| (0, reason))?;
+        Ok(campaign)
+    }
+
+    fn check_sizes(&self) -> Result<(), String> {                           // Todos los niveles deben ocupar la misma pantalla
+        let first = match self.levels.first() {
+            Some(level) => &level.map,
+            None => return Err(String::from("no hay niveles")),
+        };
+        for (i, level) in self.levels.iter().enumerate() {
+            if level.map.height() != first.height() || level.map.view_width() != first.view_width() {
+                return Err(format!("el nivel {} no tiene una pantalla de {}x{}", i + 1, first.view_width(), first.height()));
+            }
+        }
+        Ok(())
+    }
+}
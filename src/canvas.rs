@@ -5,16 +5,26 @@
  * en variables u64 con la intención de reducir el espacio que ocupan en memoria.
  * Todas las funciones están adaptadas al tamaño del canvas, y se hacen cálculos
  * para conseguir el mejor ajuste posible.
+ *
+ * Este módulo forma parte de la interfaz web: Canvas implementa el trait Renderer
+ * del núcleo (fox_hell_core), que es lo único que el jugador usa para dibujarse.
+ *
+ * Con fitted las celdas son cuadradas: se usa el tamaño de celda más grande que quepa en el canvas y el
+ * canvas se ajusta al tamaño del mapa, así un mapa de cualquier tamaño no se deforma.
+ *
+ * El status se dibuja con las posiciones de StateGraph (ver graph.rs) escaladas al tamaño del canvas: los
+ * estados son círculos con su nombre, el actual en verde, las transiciones flechas con el nombre del evento
+ * y la última en verde, y abajo el historial de transiciones.
  */
 
 use stdweb::traits::*;
 use stdweb::unstable::TryInto;
 use stdweb::web::html_element::CanvasElement;
-use stdweb::web::{document, CanvasRenderingContext2d};
+use stdweb::web::{document, CanvasRenderingContext2d, FillRule, TextAlign, TextBaseline};
 
-use crate::sprite::Sprite;
-use crate::state::State;
-use crate::gamemap::GameMap;
+use fox_hell_core::graph::{StateGraph, NODE_RADIUS};
+use fox_hell_core::renderer::Renderer;
+use fox_hell_core::sprite::Sprite;
 
 pub struct Canvas {
     pub canvas: CanvasElement,
@@ -49,68 +59,90 @@ impl Canvas {
         }
     }
 
-    pub fn draw_graph(&self, state: State, status: String) {
-        self.clear_all("white");
-
-        self.ctx.set_fill_style_color("dodgerblue");
-        self.ctx.fill_rect(130.0, 20.0, 30.0, 30.0);
-        self.ctx.fill_rect(230.0, 20.0, 30.0, 30.0);
-        self.ctx.fill_rect(80.0, 80.0, 30.0, 30.0);
-        self.ctx.fill_rect(280.0, 80.0, 30.0, 30.0);
-        self.ctx.fill_rect(130.0, 140.0, 30.0, 30.0);
-        self.ctx.fill_rect(230.0, 140.0, 30.0, 30.0);
-
-        self.ctx.set_fill_style_color("limegreen");
-        match state {
-            State::Stand => {
-                self.ctx.fill_rect(125.0, 15.0, 40.0, 40.0);
-            },
-            State::Jump(_dir) => {
-                self.ctx.fill_rect(225.0, 15.0, 40.0, 40.0);
-            },
-            State::Walk(_dir) => {
-                self.ctx.fill_rect(75.0, 75.0, 40.0, 40.0);
-            },
-            State::Crouch => {
-                self.ctx.fill_rect(275.0, 75.0, 40.0, 40.0);
-            },
-            State::Fall(_dir) => {
-                self.ctx.fill_rect(125.0, 135.0, 40.0, 40.0);
-            },
-            State::Meteor => {
-                self.ctx.fill_rect(225.0, 135.0, 40.0, 40.0);
-            },
-        }
+    pub fn fitted(attr_id: &str, width: u32, height: u32) -> Canvas {
+        let mut canvas = Canvas::new(attr_id, width, height);
+        let cell = canvas.scaled_width.min(canvas.scaled_height).max(1);
+        canvas.canvas.set_width(cell * width);
+        canvas.canvas.set_height(cell * height);
+        canvas.scaled_width = cell;
+        canvas.scaled_height = cell;
+        canvas
+    }
+}
 
-        self.ctx.set_fill_style_color("black");
+impl Renderer for Canvas {
+    fn draw_graph(&mut self, graph: &StateGraph) {
+        self.clear("white");
+        let width = self.canvas.width() as f64;
+        let height = self.canvas.height() as f64;
+        let radius = NODE_RADIUS * width.min(height);
+
+        self.ctx.set_text_align(TextAlign::Center);
+        self.ctx.set_text_baseline(TextBaseline::Middle);
+        self.ctx.set_font("10px Arial");
+        for (i, edge) in graph.edges.iter().enumerate() {
+            let (line, color) = if graph.last_edge == Some(i) { (3.0, "limegreen") } else { (1.0, "gray") };
+            self.ctx.set_stroke_style_color(color);
+            self.ctx.set_fill_style_color(color);
+            self.ctx.set_line_width(line);
+
+            let path = graph.edge_path(i);
+            self.ctx.begin_path();
+            self.ctx.move_to(path[0].0 * width, path[0].1 * height);
+            for (x, y) in path.iter().skip(1) {
+                self.ctx.line_to(x * width, y * height);
+            }
+            self.ctx.stroke();
+
+            let [tip, left, right] = graph.arrow_head(i);
+            self.ctx.begin_path();
+            self.ctx.move_to(tip.0 * width, tip.1 * height);
+            self.ctx.line_to(left.0 * width, left.1 * height);
+            self.ctx.line_to(right.0 * width, right.1 * height);
+            self.ctx.close_path();
+            self.ctx.fill(FillRule::NonZero);
+
+            let (x, y) = graph.label_pos(i);
+            self.ctx.set_fill_style_color("black");
+            self.ctx.fill_text(&edge.label, x * width, y * height, None);
+        }
 
-        self.ctx.set_font("Bold 15px Arial");
-        self.ctx.fill_text("ST", 135.0, 40.0, Some(20.0));
-        self.ctx.fill_text("JM", 235.0, 40.0, Some(20.0));
-        self.ctx.fill_text("WK", 85.0, 100.0, Some(20.0));
-        self.ctx.fill_text("CR", 285.0, 100.0, Some(20.0));
-        self.ctx.fill_text("FL", 135.0, 160.0, Some(20.0));
-        self.ctx.fill_text("MT", 235.0, 160.0, Some(20.0));
+        self.ctx.set_font("Bold 11px Arial");
+        for (i, node) in graph.nodes.iter().enumerate() {
+            self.ctx.set_fill_style_color(if graph.active == Some(i) { "limegreen" } else { "dodgerblue" });
+            self.ctx.begin_path();
+            self.ctx.arc(node.x * width, node.y * height, radius, 0.0, 2.0 * std::f64::consts::PI, false);
+            self.ctx.fill(FillRule::NonZero);
+            self.ctx.set_fill_style_color("black");
+            self.ctx.fill_text(&node.label, node.x * width, node.y * height, Some(radius * 2.0));
+        }
 
-        self.ctx.set_font("30px Arial");
-        self.ctx.fill_text(status.as_ref(), 50.0, 250.0, Some(390.0));
+        self.ctx.set_text_align(TextAlign::Left);
+        self.ctx.set_font("16px Arial");
+        for (line, text) in graph.history.iter().enumerate() {
+            let (x, y) = graph.history_pos(line);
+            self.ctx.fill_text(text, x * width, y * height, Some(width * 0.9));
+        }
+        self.ctx.set_text_align(TextAlign::Start);                          // Se regresa a los valores por defecto del canvas
+        self.ctx.set_text_baseline(TextBaseline::Alphabetic);
     }
 
-    pub fn draw_sprite(&self, x: u32, y: u32, spte: &Sprite) {
-        let x = x * self.scaled_width;
-        let y = y * self.scaled_height;
+    fn draw_sprite(&mut self, x: f64, y: f64, spte: &Sprite) {
         let width = self.scaled_width as f64 / 8.0;
         let height = self.scaled_height as f64 / 8.0;
+        let x = (x * self.scaled_width as f64).round();                    // Entre dos celdas se dibuja en el pixel más cercano
+        let y = ((y + 1.0) * self.scaled_height as f64).round() - spte.map.height() as f64 * height; // Los sprites altos crecen hacia arriba
 
         for (i, row) in spte.map.0.iter().enumerate() {
             for (j, pixel) in row.iter().enumerate() {
-                if *pixel==0 {
-                    continue;
-                }
-                self.ctx.set_fill_style_color(&spte.colorset[(*pixel-1) as usize]);
+                let color = match spte.colorset.get((*pixel as usize).wrapping_sub(1)) {
+                    Some(color) => color,
+                    None => continue,                                       // El 0 es transparente, igual que un color que no existe
+                };
+                self.ctx.set_fill_style_color(color);
                 self.ctx.fill_rect(
-                    x as f64 + j as f64 * width,
-                    y as f64 + i as f64 * width,
+                    x + j as f64 * width,
+                    y + i as f64 * height,
                     width,
                     height
                 );
@@ -118,23 +150,7 @@ impl Canvas {
         }
     }
 
-    pub fn draw_map(&self, map: &GameMap) {
-        self.clear_all(
-            if map.bg_alter { map.bg_temp.as_ref() } else { map.bg.as_ref() }
-        );
-        for (i, row) in map.map.iter().enumerate() {
-            for (j, s) in row.iter().enumerate() {
-                if *s==0 {
-                    continue;
-                }
-                let x = j as u32;
-                let y = i as u32;
-                self.draw_sprite(x, y, &map.sprites[(*s-1) as usize]);
-            }
-        }
-    }
-
-    pub fn clear_all(&self, bg: &str) {
+    fn clear(&mut self, bg: &str) {
         self.ctx.set_fill_style_color(bg);
         self.ctx.fill_rect(
             0.0,
@@ -143,4 +159,15 @@ impl Canvas {
             (self.height * self.scaled_height) as f64,
         );
     }
+
+    fn draw_text(&mut self, x: f64, y: f64, text: &str, color: &str) {
+        self.ctx.set_fill_style_color(color);
+        self.ctx.set_font(&format!("{}px Arial", self.scaled_height / 2));
+        self.ctx.fill_text(
+            text,
+            x * self.scaled_width as f64,
+            y * self.scaled_height as f64,
+            None
+        );
+    }
 }
@@ -9,32 +9,29 @@
  * ejecución del programa.
  * 
  * Se utiliza una estructura de coordenadas para facilitar su comparación y copia dentro de ciertas
- * codiciones que se necesitan entre el jugador y el mapa.
+ * codiciones que se necesitan entre el jugador y el mapa. Coord es la celda más cercana al zorro; su posición
+ * real está en unidades de sub-celda (ver physics.rs), con la que se dibuja y se revisan los choques con los
+ * proyectiles.
  * 
- * La carga de sprites se hace en tiempo de compilación puesto que es imposible leer un archivo cuando
- * el programa se encuentra en ejecución, esto viene siendo una limitación leve por parte del uso de 
- * stdweb. De ahí en fuera el comportamiento del programa es el habitual.
+ * Los sprites y los mapas se incluyen en el binario en tiempo de compilación, pero con with_assets se pueden
+ * usar archivos cargados en ejecución (ver assets.rs): la interfaz web los descarga del servidor y la
+ * terminal los lee de un directorio, así que no hace falta recompilar para probar un mapa. De ahí en fuera
+ * el comportamiento del programa es el habitual. Con reload se cambian los sprites y los mapas durante la
+ * partida sin tocar el nivel, el puntaje, las vidas ni los proyectiles; los mapas nuevos deben tener el mismo
+ * tamaño de pantalla que los anteriores.
+ *
+ * Este módulo no depende de stdweb: el juego se dibuja a través del trait Renderer, por lo que la interfaz
+ * que lo usa sólo tiene que implementarlo, y consulta graph_changed() después de cada update para saber si
+ * debe redibujar el status con draw_graph.
  * 
- * Para cargar los sprites de archivos se incluyen como referecias a string, y se crean usando la
+ * Para cargar los sprites de archivos se leen como texto desde Assets, y se crean usando la
  * clase Sprite línea por línea según la información contenida en el archivo. El archivo contiene los
  * sprites en versión comprimida, por lo que en la creación de éstos se descomprimen dando como
  * resultado un sprite imprimible por las funciones dento del módulo canvas.
  * 
- * El estado inicial del jugador es Stand y podrá cambiar siguiendo las siguientes reglas:
- *   Stand  ---     Up KeyDown     ---> Jump
- *   Stand  ---     L/R KeyDown    ---> Walk
- *   Stand  ---    Down KeyDown    ---> Crouch
- *   Jump   ---  MaxHeightReached  ---> Fall
- *   Jump   ---    Down KeyDown    ---> Meteor
- *   Fall   ---    Down KeyDown    ---> Meteor
- *   Fall   ---        Land        ---> Stand
- *   Fall   --- Land & L/R KeyDown ---> Walk
- *   Walk   ---      L/R KeyUp     ---> Stand
- *   Walk   ---     Up KeyDown     ---> Jump
- *   Walk   ---       NoFloor      ---> Fall
- *   Meteor ---        Land        ---> Crouch
- *   Crouch ---     Down KeyUp     ---> Stand
- * De cualquiera de estos estados se puede pasar a muerto y concluir la ejecución de esa sesión de juego.
+ * El estado inicial del jugador es Stand y cambia siguiendo las reglas de StateMachine_FoxHell.jff (ver fsm.rs):
+ * las teclas y el movimiento se traducen a eventos que se aplican con fire.
+ * En Jump, Fall y Meteor el zorro se mueve con la física de physics.rs.
  * 
  * La dinámica del juego consiste en evadir las rocas de lava que caen del cielo mientras se recogen las manzanas
  * que van pasando horizontalmente desde los lados. La cantidad de manzanas recogidas se muestra en la pantalla
@@ -44,23 +41,53 @@
  * en cambio las manzanas sólo aparecen de forma lateral y a alturas aleatorias.
  * 
  * Los mapas son configurables desde un archivo por lo que se puede implementar fácil un cambio en el mapa de juego.
- * Los archivos de los mapas que se muestran son mapStart.txt, map0.txt y mapEnd.txt.
+ * Los archivos de los mapas que se muestran son mapStart.txt, los mapas de los niveles y mapEnd.txt.
+ *
+ * La partida se compone de niveles (ver campaign.rs); al pasar de nivel se conservan el puntaje y las vidas.
+ * Qué celdas son suelo, cuáles hacen daño, qué objetos se recogen al agacharse (como el botiquín) y qué sprites
+ * se animan se declara en cada archivo de mapa (ver tile.rs).
+ * El tamaño del juego es el del mapa de juego (con with_map se puede usar cualquier mapa): el zorro aparece al
+ * centro de la penúltima fila, las vidas se muestran desde la columna 1 y el puntaje en las últimas cuatro
+ * columnas antes del borde, y los proyectiles se disparan dentro de ese tamaño. Las pantallas de inicio, fin
+ * y pausa se centran en el mapa de juego.
+ * Si el mapa declara una ventana visible (Viewport) más angosta que el nivel, la cámara sigue al zorro y las
+ * vidas y el puntaje se dibujan encima del mapa en la fila superior de la pantalla. Los proyectiles se
+ * disparan desde los bordes de la ventana visible. Sólo si el mapa lo indica (Wrap) se da la vuelta al salir
+ * por un lado, si no el zorro se detiene en los bordes del nivel.
+ *
+ * Durante el juego se puede pausar la partida (GameState::Paused): mientras tanto no avanzan las iteraciones,
+ * los proyectiles ni las animaciones y se dibuja mapPause.txt encima del juego. Las teclas de la pausa se
+ * aplican al reanudar (ver PausedKeys en snapshot.rs).
  * 
  * Para generar las animaciones o acciones periódicas se utilizó un contador de iteraciones para que cada ciertos
  * intervalos de tiempo se puedan ejecutar ciertas acciones, por ejemplo, los cambios de sprite para simular
  * animaciones o el movimiento de las nubes.
  */
 
-use crate::canvas::Canvas;                                                  // Uso de los otros
-use crate::direction::Direction;                                            // módulos desarrollados
+use std::collections::HashMap;
+
+use crate::direction::Direction;                                            // Uso de los otros módulos desarrollados
 use crate::sprite::Sprite;                                                  // para completar la
 use crate::gamemap::GameMap;                                                // ejecución del juego
 use crate::state::State;                                                    // en secciones por
-use crate::projectile::Projectile;                                          // separado para mayor
+use crate::fsm::{Event, StateMachine};
+use crate::graph::{StateGraph, HISTORY_LEN};
+use crate::projectile::{Projectile, ProjectileCoord};                        // separado para mayor
 use crate::gamestate::GameState;                                            // organización.
+use crate::input::Input;
+use crate::physics::{cell, descend, fall_speed, interpolate, overlap, rise, walk};
+use crate::physics::{CUT_SPEED, HIT_MARGIN, JUMP_SPEED, METEOR_SPEED, UNITS};
+use crate::random::{RandomSource, XorShift};
+use crate::renderer::Renderer;
+use crate::snapshot::{MapSnapshot, ProjectileKind, ProjectileSnapshot, Snapshot, SNAPSHOT_VERSION};
+use crate::camera::Camera;
+use crate::assets::Assets;
+use crate::campaign::{Campaign, CampaignSource};
+use crate::theme::Theme;
+use crate::tile::{HudRole, Pickup};
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub struct Coord(u32, u32);                                                 // Estructura de coordenadas
+pub struct Coord(pub u32, pub u32);                                         // Estructura de coordenadas
 
 #[derive(Debug)]
 pub struct Character {                                                      // Estructura de datos del jugador que contiene toda la información útil para dar la jugabilidad al juego
@@ -69,6 +96,10 @@ pub struct Character {                                                      // E
     state: State,
     pub last_state: State,
     stt_ix: usize,
+    machine: StateMachine,
+    hits: Vec<u32>,
+    last_transition: Option<usize>,
+    history: Vec<String>,
     height: u32,
     width: u32,
     dir: Direction,
@@ -78,29 +109,151 @@ pub struct Character {                                                      // E
 
     it: usize,
     max_it: usize,
+    tick: u64,
+    game_over_tick: Option<u64>,
 
     map_start: GameMap,
     map: GameMap,
     map_end: GameMap,
+    map_pause: GameMap,
+    map_level: GameMap,
+    map_victory: GameMap,
+    view_width: u32,
+    hud: Vec<u8>,
 
-    jump_h: u8,
-    max_jump_h: u8,
+    pos_x: i32,                                                             // Posición del zorro en unidades de physics.rs
+    pos_y: i32,
+    vel_y: i32,                                                             // Velocidad vertical, positiva hacia abajo
+    last_pos: (i32, i32),                                                   // Posición antes del último update, para dibujar entre las dos
 
     left_key: bool,
     right_key: bool,
     down_key: bool,
+    up_key: bool,
+    paused_keys: (bool, bool, bool),
 
     ices: Vec<Projectile>,
     apples: Vec<Projectile>,
+    lava_ball: Sprite,                                                      // Sprites de los proyectiles como están en fox_sprites.txt, sin el tema
+    apple: Sprite,
 
     score: u16,
 
     lifes: u8,
+
+    random: Box<dyn RandomSource>,
+
+    campaign: Campaign,
+    level: usize,
+    level_tick: u64,
+    level_score: u16,
+
+    assets: Assets,
+    themes: Vec<Theme>,
+    theme: usize,
 }
 
+const TRANSITION_TICKS: u64 = 66;                                           // Duración de la pantalla entre niveles (unos 3 segundos)
+const TICK_MS: u64 = 45;                                                    // Milisegundos entre cada update en las interfaces
+
 impl Character {
-    pub fn new(width: u32, height: u32) -> Character {                      // Constructor de la clase, donde se definen todos los valores necesarios para la correcta ejecución del juego
-        let coord = Coord(9, 8);
+    pub fn new(seed: u64) -> Character {                                    // Constructor de la clase con el generador por defecto iniciado con la semilla indicada
+        Character::with_random(Box::new(XorShift::new(seed)))
+    }
+
+    pub fn with_random(random: Box<dyn RandomSource>) -> Character {        // Constructor con los niveles por defecto (levels.txt)
+        Character::with_campaign(Campaign::embedded(), random)
+    }
+
+    pub fn with_map(map: GameMap, random: Box<dyn RandomSource>) -> Character { // Constructor de una partida sin fin en un solo mapa
+        Character::with_campaign(Campaign::single(map), random)
+    }
+                                                                            // Constructor de la clase, donde se definen todos los valores necesarios para la correcta ejecución del juego
+    pub fn with_campaign(campaign: Campaign, random: Box<dyn RandomSource>) -> Character {
+        Character::with_assets(campaign, Assets::embedded(), random)
+    }
+                                                                            // Constructor que toma los sprites y las pantallas de los archivos de assets
+    pub fn with_assets(campaign: Campaign, assets: Assets, random: Box<dyn RandomSource>) -> Character {
+        let map = campaign.levels[0].map.clone();
+        let width = map.width();
+        let height = map.height();
+        let view_width = map.view_width();
+        let coord = Character::spawn_coord(width, height);
+        let sprites = Character::load_sprites(&assets);
+        let (lava_ball, apple) = Character::projectile_sprites(&assets);
+        let themes = Theme::from_assets(&assets);
+        let machine = StateMachine::embedded();
+        let initial = State::from_name(machine.initial(), Direction::None).unwrap_or(State::Stand);
+        let hits = vec![0; machine.transitions.len()];
+
+        Character {
+            coord,
+            sprites,
+
+            state: initial,
+            last_state: initial,
+            stt_ix: 0,
+            machine,
+            hits,
+            last_transition: None,
+            history: Vec::new(),
+
+            game_state: GameState::Start,
+
+            height,
+            width,
+
+            dir: Direction::Right,
+            last_dir: Direction::Right,
+
+            it: 0,
+            max_it: 200,
+            tick: 0,
+            game_over_tick: None,
+
+            map_start: Character::screen_map(&assets, "mapStart.txt").centered(view_width, height),
+            map,
+            map_end: Character::screen_map(&assets, "mapEnd.txt").centered(view_width, height),
+            map_pause: Character::screen_map(&assets, "mapPause.txt").centered(view_width, height),
+            map_level: Character::screen_map(&assets, "mapLevel.txt").centered(view_width, height),
+            map_victory: Character::screen_map(&assets, "mapVictory.txt").centered(view_width, height),
+            view_width,
+            hud: vec![0; view_width as usize],
+
+            pos_x: coord.0 as i32 * UNITS,
+            pos_y: coord.1 as i32 * UNITS,
+            vel_y: 0,
+            last_pos: (coord.0 as i32 * UNITS, coord.1 as i32 * UNITS),
+
+            left_key: false,
+            right_key: false,
+            down_key: false,
+            up_key: false,
+            paused_keys: (false, false, false),
+
+            ices: Vec::new(),
+            apples: Vec::new(),
+            lava_ball,
+            apple,
+
+            score: 0,
+
+            lifes: 2,
+
+            random,
+
+            campaign,
+            level: 0,
+            level_tick: 0,
+            level_score: 0,
+
+            assets,
+            themes,
+            theme: 0,
+        }
+    }
+
+    fn load_sprites(assets: &Assets) -> [Vec<Sprite>; 5] {              // Función que arma las animaciones del zorro con los sprites de fox_sprites.txt
         let mut sprites = [
             Vec::new(),
             Vec::new(),
@@ -109,7 +262,7 @@ impl Character {
             Vec::new(),
         ];
 
-        let hash = Sprite::hash_from_text(String::from(include_str!("files/fox_sprites.txt")));
+        let hash = Character::fox_sprites(assets);
 
         let spte: Sprite = hash.get("Stand1").unwrap().deref();
         let spte2: Sprite = hash.get("Stand2").unwrap().deref();
@@ -148,43 +301,7 @@ impl Character {
         let spte: Sprite = hash.get("Crouch").unwrap().deref();
         sprites[4].push(spte);
 
-        Character {
-            coord,
-            sprites,
-
-            state: State::Stand,
-            last_state: State::Stand,
-            stt_ix: 0,
-
-            game_state: GameState::Start,
-
-            height,
-            width,
-
-            dir: Direction::Right,
-            last_dir: Direction::Right,
-
-            it: 0,
-            max_it: 200,
-
-            map_start: GameMap::from_text(String::from(include_str!("files/mapStart.txt"))),
-            map: GameMap::from_text(String::from(include_str!("files/map0.txt"))),
-            map_end: GameMap::from_text(String::from(include_str!("files/mapEnd.txt"))),
-
-            jump_h: 0,
-            max_jump_h: 4,
-
-            left_key: false,
-            right_key: false,
-            down_key: false,
-            
-            ices: Vec::new(),
-            apples: Vec::new(),
-
-            score: 0,
-
-            lifes: 2,
-        }
+        sprites
     }
 
     pub fn change_dir(&mut self, dir: Direction) {                          // Función que cambia la dirección del personaje
@@ -195,34 +312,36 @@ impl Character {
         self.dir = dir;
     }
 
-    pub fn update(&mut self, graph: &Canvas) {                              // Función que actualiza el proceso del juego
-        if self.game_state == GameState::Start || self.game_state == GameState::GameOver {
-            if self.it % 2 == 0 {
-                self.map_start.sprites[(('F' as u8) - ('A' as u8) + (9 as u8) ) as usize].map.invert_side();
-                self.map_end.sprites[(('O' as u8) - ('A' as u8) + (9 as u8) ) as usize].map.invert_side();
-                self.map_end.sprites[7 as usize].map.invert_side();
-            }
-            if self.it % 10 == 0 {
-                self.map_start.sprites[(('G' as u8) - ('A' as u8) + (9 as u8) ) as usize].map.invert_side();
-                self.map_start.sprites[(('D' as u8) - ('A' as u8) + (9 as u8) ) as usize].map.invert_side();
-                self.map_end.sprites[9 as usize].map.invert_side();
-                self.map_end.sprites[(('P' as u8) - ('A' as u8) + (9 as u8) ) as usize].map.invert_side();
+    pub fn update(&mut self) {                                              // Función que actualiza el proceso del juego
+        self.tick += 1;
+        if self.game_state == GameState::Paused {
+            return
+        }
+        if self.game_state == GameState::Start || self.game_state == GameState::GameOver || self.game_state == GameState::Victory {
+            self.map_start.animate(self.it);                                 // Cada mapa indica qué sprites se animan y cada cuántas iteraciones
+            self.map_end.animate(self.it);
+            self.map_victory.animate(self.it);
+            self.it = if self.it < self.max_it - 1 { self.it + 1 } else { 0 };
+            return
+        }
+        if self.game_state == GameState::LevelComplete {
+            self.map_level.animate(self.it);
+            self.level_tick += 1;
+            if self.level_tick >= TRANSITION_TICKS {
+                self.next_level();
             }
             self.it = if self.it < self.max_it - 1 { self.it + 1 } else { 0 };
             return
         }
 
+        self.level_tick += 1;
         self.load_hud();
+        self.last_pos = (self.pos_x, self.pos_y);
 
-        if Character::keep_track(&mut self.ices, &self.coord, false) {
-            if self.lifes > 0 {
-                self.lifes-=1;
-            }
-            else {
-                self.game_state = GameState::GameOver;
-            }
+        if Character::keep_track(&mut self.ices, (self.pos_x, self.pos_y), false, HIT_MARGIN) {
+            self.hit();
         }
-        if Character::keep_track(&mut self.apples, &self.coord, false) {
+        if Character::keep_track(&mut self.apples, (self.pos_x, self.pos_y), false, 0) {
             self.score += 1;
         }
         if !self.left_key && !self.right_key {
@@ -232,86 +351,61 @@ impl Character {
 
         if self.get_state() == State::Crouch {
             if !self.down_key {
-                self.set_state(State::Stand);
-            }
-            if self.map.map[self.coord.1 as usize][self.coord.0 as usize] == 6 && self.lifes<4 {
-                self.lifes+=1;
-                self.map.map[self.coord.1 as usize][self.coord.0 as usize] = 0;
+                self.fire(Event::DownKeyUp, Direction::None);
             }
+            self.pick_up();
         }
+        let last_coord = (self.coord.0, self.coord.1);
         match self.state {
             State::Walk(dir) => {
-                if self.it % 2 == 0 {
-                    self.change_dir(dir);
-                    let new_coord = match dir {
-                        Direction::Right => Coord( (self.coord.0 + 1) % self.width, self.coord.1 ),
-                        Direction::Left => Coord( (self.coord.0 + self.width - 1) % self.width, self.coord.1 ),
-                        Direction::None => Coord( self.coord.0 % self.width, self.coord.1 ),
-                    };
-                    self.coord = new_coord;
-                    if self.no_floor() {
-                        self.set_state(State::Fall(dir));
-                    }
+                self.change_dir(dir);
+                self.set_pos_x(self.step_x(dir));
+                if self.no_floor() {
+                    self.fire(Event::NoFloor, dir);
                 }
             },
             State::Jump(dir) => {
-                if self.it % 2 == 0 {
-                    self.change_dir(dir);
-                    if self.jump_h < self.max_jump_h {
-                        let coord_y = if self.coord.1+self.height-1<self.height { self.coord.1 } else { self.coord.1-1 };
-                        let new_coord = match dir {
-                            Direction::Right => Coord( (self.coord.0 + 1) % self.width, coord_y ),
-                            Direction::Left => Coord( (self.coord.0 + self.width - 1) % self.width, coord_y ),
-                            Direction::None => Coord( self.coord.0 % self.width, coord_y ),
-                        };
-                        self.coord = new_coord;
-                        self.jump_h += 1;
-                    } else {
-                        self.set_state(State::Fall(self.dir));
-                        self.jump_h = 0;
-                    }
+                self.change_dir(dir);
+                self.set_pos_x(self.step_x(dir));
+                let (pos_y, vel_y) = rise(self.pos_y, self.vel_y, self.up_key);  // Al subir se atraviesan las plataformas
+                self.set_pos_y(pos_y);
+                self.vel_y = vel_y;
+                if vel_y == 0 {
+                    self.fire(Event::MaxHeightReached, self.dir);
                 }
             },
             State::Fall(dir) => {
-                if self.it % 2 == 0 {
-                    self.change_dir(dir);
-                    if self.no_floor() {
-                        let new_coord = match dir {
-                            Direction::Right => Coord( (self.coord.0 + 1) % self.width, self.coord.1 + 1 ),
-                            Direction::Left => Coord( (self.coord.0 + self.width - 1) % self.width, self.coord.1 + 1 ),
-                            Direction::None => Coord( self.coord.0 % self.width, self.coord.1 + 1 ),
-                        };
-                        self.coord = new_coord;
-                        self.set_state(State::Fall(self.dir));
-                    }
-                    else {
-                        if self.get_dir() == Direction::None {
-                            self.set_state(State::Stand);
-                        }
-                        else {
-                            self.set_state(State::Walk(self.get_dir()));
-                        }
-
-                    }
+                self.change_dir(dir);
+                if !self.landed() {
+                    self.set_pos_x(self.step_x(dir));
+                }
+                self.vel_y = fall_speed(self.vel_y);
+                if !self.descend(self.vel_y) {
+                    self.steer(self.dir);
+                }
+                else if self.get_dir() == Direction::None {
+                    self.fire(Event::Land, Direction::None);
+                }
+                else {
+                    self.fire(Event::LandMoving, self.get_dir());
                 }
             },
             State::Meteor => {
-                self.jump_h = 0;
-                if self.no_floor() {
-                    self.coord = Coord( self.coord.0 % self.width, self.coord.1 + 1 );
-                    self.set_state(State::Meteor);
+                if !self.descend(METEOR_SPEED) {
+                    self.steer(Direction::None);                           // Sigue en Meteor
                 }
                 else {
-                    self.set_state(State::Crouch);
+                    self.fire(Event::Land, Direction::None);
                 }
             }
-            State::Stand => {
-                if self.it % 2 == 0 && self.no_floor() {
-                    self.set_state(State::Fall(Direction::None));
-                }
+            State::Stand if self.it.is_multiple_of(2) && self.no_floor() => {
+                self.fire(Event::NoFloor, Direction::None);
             },
             _ => (),
         }
+        if last_coord != (self.coord.0, self.coord.1) && self.map.properties(self.coord.0, self.coord.1).hazard {
+            self.hit();                                                     // Entrar en una celda peligrosa quita una vida
+        }
 
         for proj in self.ices.iter_mut() {
             proj.spte.map.right_rotate();
@@ -321,81 +415,215 @@ impl Character {
             proj.spte.map.invert_side();
         }
 
-        if self.it % 2 == 0 {
-            self.map.sprites[6].map.invert_side();
+        if Character::keep_track(&mut self.ices, (self.pos_x, self.pos_y), true, HIT_MARGIN) {
+            self.hit();
         }
 
-        if self.it%4==0 {
-            if Character::keep_track(&mut self.ices, &self.coord, true) {
-                if self.lifes > 0 {
-                    self.lifes-=1;
-                }
-                else {
-                    self.game_state = GameState::GameOver;
-                }
-            }
-
-            if Character::keep_track(&mut self.apples, &self.coord, true) {
-                self.score += 1;
-            }
+        if Character::keep_track(&mut self.apples, (self.pos_x, self.pos_y), true, 0) {
+            self.score += 1;
         }
 
-        if self.it % 10 == 0 {
-            self.map.sprites[2].map.invert_side();
-            self.map.sprites[0].map.invert_side();
-        }
+        self.map.animate(self.it);
 
-        if self.it%20==0 {
-            let hash = Sprite::hash_from_text(String::from(include_str!("files/fox_sprites.txt")));
-            let mut lball = hash.get("LavaBall").unwrap().deref();
+        let camera = self.camera();                                         // Los proyectiles se disparan dentro de la ventana visible
+        let screen_x = camera.to_screen(self.coord.0 as i32).unwrap_or(0);
+        if self.it.is_multiple_of(self.campaign.levels[self.level].ice_rate) {
+            let mut lball = self.themed(&self.lava_ball);
             lball.map.invert_side();
             let mut proj = Projectile::new(&lball);
-            proj.shoot_at(self.coord.0, self.coord.1, self.width, self.height, true);
+            proj.shoot_at(screen_x, self.coord.1, camera.width, self.height, true, self.random.as_mut());
+            proj.to_world(&camera);
             self.ices.push(proj);
+        }
 
-            let hash = Sprite::hash_from_text(String::from(include_str!("files/fox_sprites.txt")));
-            let apl = hash.get("Apple").unwrap().deref();
+        if self.it.is_multiple_of(self.campaign.levels[self.level].apple_rate) {
+            let apl = self.themed(&self.apple);
             let mut proj = Projectile::new(&apl);
-            proj.shoot_at(self.coord.0, self.coord.1, self.width, self.height, false);
+            proj.shoot_at(screen_x, self.coord.1, camera.width, self.height, false, self.random.as_mut());
+            proj.to_world(&camera);
             self.apples.push(proj);
         }
 
+        if self.game_state == GameState::Play {
+            let level = &self.campaign.levels[self.level];
+            if level.goal > 0 && self.score.saturating_sub(self.level_score) >= level.goal {
+                self.complete_level();
+            } else if level.duration > 0 && self.level_tick >= level.duration {
+                self.game_state = GameState::GameOver;                      // Se acabó el tiempo del nivel
+                self.game_over_tick = Some(self.tick);
+            }
+        }
+
         self.it = if self.it < self.max_it - 1 { self.it + 1 } else { 0 };
+    }
 
-        if self.last_state != self.state || self.state == State::Meteor {
-            self.draw_graph(graph, self.state, self.get_transition());
+    fn complete_level(&mut self) {                                      // Función que termina el nivel actual al alcanzar su meta
+        if self.campaign.arena.is_some() && self.level + 1 == self.campaign.levels.len() {
+            let seed = self.random.next_u64();                          // En el modo sin fin siempre hay una arena más
+            self.campaign.add_arena(seed, &self.assets);
+        }
+        if self.level + 1 < self.campaign.levels.len() {
+            self.game_state = GameState::LevelComplete;
+        } else {
+            self.game_state = GameState::Victory;
         }
+        self.level_tick = 0;
+        self.load_hud();
     }
 
-    pub fn draw(&self, canvas: &Canvas) {                               // Función que dibuja el juego en pantalla
-        if self.game_state == GameState::Start {
-            canvas.draw_map(&self.map_start);
-            return
+    fn next_level(&mut self) {                                          // Función que pasa de la pantalla de transición al siguiente nivel
+        self.load_level(self.level + 1);
+        self.game_state = GameState::Play;
+    }
+
+    fn load_level(&mut self, level: usize) {                            // Función que carga el mapa de un nivel y coloca al jugador al inicio
+        self.level = level;
+        self.map = self.campaign.levels[level].map.clone();
+        self.themes[self.theme].apply_map(&mut self.map, &self.campaign.levels[level].map);
+        self.width = self.map.width();
+        self.height = self.map.height();
+        self.level_tick = 0;
+        self.level_score = self.score;
+
+        if self.last_dir == Direction::Left {
+            self.invert_side();
+        }
+        self.coord = Character::spawn_coord(self.width, self.height);
+        self.pos_x = self.coord.0 as i32 * UNITS;
+        self.pos_y = self.coord.1 as i32 * UNITS;
+        self.last_pos = (self.pos_x, self.pos_y);
+
+        self.state = self.initial_state();
+        self.last_state = self.state;
+        self.stt_ix = 0;
+
+        self.dir = Direction::Right;
+        self.last_dir = Direction::Right;
+
+        self.vel_y = 0;
+
+        self.ices.clear();
+        self.apples.clear();
+    }
+
+    pub fn graph_changed(&self) -> bool {                               // Función que indica si el status del juego debe volver a dibujarse tras el último update
+        if self.game_state != GameState::Play {
+            return false;
         }
+        self.last_state != self.state || self.state == State::Meteor
+    }
 
-        if self.game_state == GameState::GameOver {
-            canvas.draw_map(&self.map_end);
+    pub fn draw<R: Renderer + ?Sized>(&self, renderer: &mut R) {        // Función que dibuja el juego en pantalla tal como quedó en el último update
+        self.draw_interpolated(renderer, 1.0);
+    }
+                                                                        // Función que dibuja el juego con el zorro y los proyectiles entre su posición
+                                                                        // anterior y la actual, alpha es la fracción del update que ya pasó
+    pub fn draw_interpolated<R: Renderer + ?Sized>(&self, renderer: &mut R, alpha: f64) {
+        let screen = Camera::fixed(self.view_width);
+        if self.game_state != GameState::Play && self.game_state != GameState::Paused {
+            renderer.draw_map(self.current_map(), &screen);
+            if self.game_state == GameState::Start && self.themes.len() > 1 {  // En la pantalla de inicio se muestra el tema que se puede cambiar con T
+                let text = format!("Tema: {} (T)", self.theme().name);
+                renderer.draw_text(0.5, self.height as f64 - 0.25, &text, "white");
+            }
             return
         }
+        let camera = self.camera();
+        renderer.draw_map(self.current_map(), &camera);
+
+        for (x, tile) in self.hud.iter().enumerate() {                  // Las vidas y el puntaje no se desplazan con la cámara
+            if *tile != 0 {
+                if let Some(spte) = self.map.sprites.get(*tile as usize - 1) {
+                    renderer.draw_sprite(x as f64, 0.0, spte);
+                }
+            }
+        }
 
-        canvas.draw_map(&self.map);
+        let alpha = if self.game_state == GameState::Paused { 1.0 } else { alpha };
+        let (x, y) = interpolate(self.last_pos, (self.pos_x, self.pos_y), alpha);
+        Character::draw_at(renderer, &camera, x, y, self.current_sprite());
 
-        canvas.draw_sprite(self.coord.0, self.coord.1, &self.sprites[self.stt_ix][self.it % self.sprites[self.stt_ix].len()]);
+        for proj in self.apples.iter().chain(self.ices.iter()) {
+            let (x, y) = interpolate(proj.last_coord(), (proj.coord.x, proj.coord.y), alpha);
+            Character::draw_at(renderer, &camera, x, y, &proj.spte);
+        }
 
-        for proj in self.apples.iter() {
-            canvas.draw_sprite(proj.coord.x as u32, proj.coord.y as u32, &proj.spte);
+        if self.game_state == GameState::Paused {
+            renderer.draw_tiles(&self.map_pause, &screen);
         }
+    }
 
-        for proj in self.ices.iter() {
-            canvas.draw_sprite(proj.coord.x as u32, proj.coord.y as u32, &proj.spte);
+    fn draw_at<R: Renderer + ?Sized>(renderer: &mut R, camera: &Camera, x: f64, y: f64, spte: &Sprite) { // Función que dibuja un sprite en una posición del nivel en celdas, si se ve en pantalla
+        let column = x.floor();
+        if let Some(screen_x) = camera.to_screen(column as i32) {
+            renderer.draw_sprite(screen_x as f64 + (x - column), y, spte);
         }
     }
-                                                                        // Función que muestra el status del juego en el canvas de status
-    pub fn draw_graph(&self, graph: &Canvas, state: State, status: String) {
-        if self.game_state == GameState::Start || self.game_state == GameState::GameOver {
+
+    pub fn camera(&self) -> Camera {                                    // Función que regresa la cámara que sigue al zorro
+        Camera::follow(self.coord.0, self.view_width, self.width, self.map.wrap)
+    }
+
+    fn step_x(&self, dir: Direction) -> i32 {                           // Función que regresa la posición en unidades a la que se mueve el zorro en la dirección indicada
+        let dx = match dir {
+            Direction::Right => 1,
+            Direction::Left => -1,
+            Direction::None => 0,
+        };
+        walk(self.pos_x, dx, self.width, self.map.wrap)
+    }
+
+    pub fn draw_graph<R: Renderer + ?Sized>(&self, graph: &mut R) {    // Función que muestra el status del juego en el canvas de status
+        if self.game_state != GameState::Play {
             return;
         }
-        graph.draw_graph(state, status);
+        graph.draw_graph(&self.state_graph());
+    }
+
+    pub fn state_graph(&self) -> StateGraph {                           // Función que arma el diagrama del status con el estado actual y las últimas transiciones
+        let mut graph = StateGraph::from_machine(&self.machine);
+        graph.active = self.machine.index(self.state.name());
+        graph.last_edge = self.last_transition;
+        graph.history = self.history.clone();
+        graph
+    }
+
+    pub fn current_map(&self) -> &GameMap {                             // Función que regresa el mapa que se debe mostrar según el estado del juego
+        match self.game_state {
+            GameState::Start => &self.map_start,
+            GameState::Play | GameState::Paused => &self.map,
+            GameState::GameOver => &self.map_end,
+            GameState::LevelComplete => &self.map_level,
+            GameState::Victory => &self.map_victory,
+        }
+    }
+
+    fn screen_map(assets: &Assets, file: &str) -> GameMap {             // Función que regresa una pantalla de assets (mapStart.txt, mapEnd.txt...)
+        GameMap::from_text(assets.text(file))                           // Igual que en fox_sprites, el incluido es sólo por si acaso
+            .or_else(|_| GameMap::from_text(Assets::embedded().text(file)))
+            .unwrap_or_default()
+    }
+
+    fn fox_sprites(assets: &Assets) -> HashMap<String, Sprite> {        // Función que regresa los sprites de fox_sprites.txt por nombre
+        Sprite::hash_from_text(assets.text("fox_sprites.txt"))             // Assets sólo acepta archivos que pasan validate.rs, así que
+            .or_else(|_| Sprite::hash_from_text(Assets::embedded().text("fox_sprites.txt")))  // el incluido es sólo por si acaso
+            .unwrap_or_default()
+    }
+
+    fn projectile_sprites(assets: &Assets) -> (Sprite, Sprite) {        // Función que regresa los sprites LavaBall y Apple de fox_sprites.txt
+        let mut hash = Character::fox_sprites(assets);                  // validate.rs exige los dos, el sprite vacío es sólo por si acaso
+        let mut take = |name: &str| hash.remove(name).unwrap_or_else(|| Sprite::new(Vec::new(), (0, 0, 0)));
+        (take("LavaBall"), take("Apple"))
+    }
+
+    fn themed(&self, original: &Sprite) -> Sprite {                     // Función que regresa una copia del sprite con los colores del tema
+        let mut spte = original.deref();
+        self.themes[self.theme].apply_sprite(&mut spte, original);
+        spte
+    }
+
+    pub fn current_sprite(&self) -> &Sprite {                           // Función que regresa el sprite del jugador en la iteración actual
+        &self.sprites[self.stt_ix][self.it % self.sprites[self.stt_ix].len()]
     }
 
     pub fn invert_side(&mut self) {                                     // Función que invierte la dirección de los sprites del personaje
@@ -406,13 +634,92 @@ impl Character {
         }
     }
 
-    pub fn no_floor(&mut self) -> bool {                                // Función para saber si hay suelo o no debajo del personaje
-        self.map.map[self.coord.1 as usize + 1][self.coord.0 as usize] != 2
+    pub fn no_floor(&self) -> bool {                                    // Función para saber si hay suelo o no debajo del personaje
+        !self.map.properties(self.coord.0, self.coord.1 + 1).solid
+    }
+
+    fn landed(&self) -> bool {                                          // Función para saber si el zorro está parado justo sobre una celda sólida
+        self.pos_y % UNITS == 0 && !self.no_floor()
+    }
+
+    fn set_pos_x(&mut self, pos_x: i32) {                               // Función que cambia la posición horizontal en unidades y pone al zorro en la columna más cercana
+        self.pos_x = pos_x;
+        self.coord = Coord( cell(pos_x) % self.width, self.coord.1 );
+    }
+
+    fn set_pos_y(&mut self, pos_y: i32) {                               // Función que cambia la altura en unidades y pone al zorro en la fila más cercana
+        self.pos_y = pos_y;
+        self.coord = Coord( self.coord.0, cell(pos_y) );
+    }
+
+    fn descend(&mut self, amount: i32) -> bool {                        // Función que baja al zorro hasta amount unidades y regresa si llegó al suelo
+        let x = self.coord.0;
+        let (pos_y, landed) = descend(self.pos_y, amount, |row| self.map.properties(x, row + 1).solid);
+        self.set_pos_y(pos_y);
+        if landed {
+            self.vel_y = 0;
+        }
+        landed
+    }
+
+    fn hit(&mut self) {                                                 // Función que quita una vida, o termina el juego si ya no quedan
+        if self.lifes > 0 {
+            self.lifes-=1;
+        }
+        else {
+            self.game_state = GameState::GameOver;
+            self.game_over_tick = Some(self.tick);
+            self.fire(Event::Die, Direction::None);
+        }
+    }
+
+    fn pick_up(&mut self) {                                             // Función que recoge el objeto de la celda en la que está agachado el jugador
+        let (x, y) = (self.coord.0, self.coord.1);
+        let picked = match self.map.properties(x, y).pickup {
+            Some(Pickup::Life) if self.lifes < 4 => {
+                self.lifes+=1;
+                true
+            },
+            Some(Pickup::Score(points)) => {
+                self.score = self.score.saturating_add(points);
+                true
+            },
+            _ => false,
+        };
+        if picked {
+            self.map.map[y as usize][x as usize] = 0;
+        }
     }
 }
 
 impl Character {
-    pub fn set_state(&mut self, state: State) {                         // Función que se encarga de realizar el cambio de estado
+    fn fire(&mut self, event: Event, dir: Direction) -> bool {          // Función que aplica un evento a la máquina de estados, regresa falso si el diagrama no tiene la transición
+        let index = match self.machine.find(self.state.name(), event) {
+            Some(index) => index,
+            None => return false,
+        };
+        self.hits[index] += 1;
+        self.last_transition = Some(index);
+        let to = self.machine.states[self.machine.transitions[index].to].name.clone();
+        if self.history.len() == HISTORY_LEN {
+            self.history.remove(0);
+        }
+        self.history.push(format!("{} -> {}", self.state.name(), to));  // Igual que get_transition después del cambio
+        if let Some(state) = State::from_name(&to, dir) {               // Death no es un estado del jugador
+            self.set_state(state);
+        }
+        true
+    }
+
+    fn steer(&mut self, dir: Direction) {                               // Función que cambia sólo la dirección del estado, que no es una transición
+        self.set_state(self.state.with_dir(dir));
+    }
+
+    fn initial_state(&self) -> State {                                  // Función que regresa el estado inicial del diagrama
+        State::from_name(self.machine.initial(), Direction::None).unwrap_or(State::Stand)
+    }
+
+    fn set_state(&mut self, state: State) {                             // Función que se encarga de realizar el cambio de estado
         self.last_state = self.state;
         self.state = state;
         self.stt_ix = match state {
@@ -441,68 +748,64 @@ impl Character {
         self.dir
     }
 
+    pub fn get_tick(&self) -> u64 {                                     // Función para obtener el número de updates ejecutados desde que se creó el jugador
+        self.tick
+    }
+
+    pub fn get_game_over_tick(&self) -> Option<u64> {                   // Función para obtener el update en que terminó la última partida
+        self.game_over_tick
+    }
+
+    pub fn get_campaign_source(&self) -> &CampaignSource {              // Función para obtener de dónde salieron los niveles de la partida
+        &self.campaign.source
+    }
+
+    pub fn get_coord(&self) -> Coord {                                  // Función para obtener las coordenadas del jugador
+        self.coord
+    }
+
+    pub fn get_score(&self) -> u16 {                                    // Función para obtener el puntaje
+        self.score
+    }
+
+    pub fn get_lifes(&self) -> u8 {                                     // Función para obtener las vidas restantes
+        self.lifes
+    }
+
+    pub fn get_ices(&self) -> &[Projectile] {                           // Función para obtener las rocas de lava en pantalla
+        &self.ices
+    }
+
+    pub fn get_apples(&self) -> &[Projectile] {                         // Función para obtener las manzanas en pantalla
+        &self.apples
+    }
+
     pub fn get_transition(&self) -> String {                            // Función que nos indica la transición de estados que se realizó
-        let st1 = match self.last_state {
-            State::Stand => {
-                "Stand"
-            },
-            State::Jump(_dir) => {
-                "Jump"
-            },
-            State::Walk(_dir) => {
-                "Walk"
-            },
-            State::Crouch => {
-                "Crouch"
-            },
-            State::Fall(_dir) => {
-                "Fall"
-            },
-            State::Meteor => {
-                "Meteor"
-            },
-        };
-        let st2 = match self.state {
-            State::Stand => {
-                "Stand"
-            },
-            State::Jump(_dir) => {
-                "Jump"
-            },
-            State::Walk(_dir) => {
-                "Walk"
-            },
-            State::Crouch => {
-                "Crouch"
-            },
-            State::Fall(_dir) => {
-                "Fall"
-            },
-            State::Meteor => {
-                "Meteor"
-            },
-        };
-        let trans = format!("{} -> {}", st1, st2);
-        String::from(trans)
+        format!("{} -> {}", self.last_state.name(), self.state.name())
+    }
+
+    pub fn machine(&self) -> &StateMachine {                            // Función que regresa la máquina de estados que sigue el jugador
+        &self.machine
+    }
+
+    pub fn transition_hits(&self) -> &[u32] {                           // Función que regresa cuántas veces se usó cada transición de la máquina desde que se creó el jugador
+        &self.hits
     }
 
-    pub fn crash(coord: &Coord, proj: &Projectile) -> bool {            // Función que compara las coordenadas del jugador con las del proyectil para detectar la colisión
-        coord.0 == proj.coord.x as u32 &&
-            coord.1 == proj.coord.y as u32
+    pub fn crash(pos: (i32, i32), proj: &Projectile, margin: i32) -> bool { // Función que revisa si la caja del jugador se traslapa con la del proyectil
+        overlap(pos, (proj.coord.x, proj.coord.y), margin)
     }
                                                                         // Función que mantiene el curso de los proyectiles en la pantalla
-    pub fn keep_track(projectiles: &mut Vec<Projectile>, coord: &Coord, move_b: bool) -> bool {
+    pub fn keep_track(projectiles: &mut Vec<Projectile>, pos: (i32, i32), move_b: bool, margin: i32) -> bool {
         let mut result = false;
-        let mut i=0;
         let mut rem:Vec<usize> = Vec::new();
-        for proj in projectiles.iter_mut() {
-            if ( move_b && !proj.next_coord() ) || Character::crash(coord, proj) {
+        for (i, proj) in projectiles.iter_mut().enumerate() {
+            if ( move_b && !proj.next_coord() ) || Character::crash(pos, proj, margin) {
                 rem.push(i);
             }
-            if Character::crash(coord, proj) {
+            if Character::crash(pos, proj, margin) {
                 result = true;
             }
-            i+=1;
         }
         for (fix, i) in rem.iter().enumerate() {
             projectiles.remove(*i - fix);
@@ -511,100 +814,287 @@ impl Character {
     }
 
     pub fn load_hud(&mut self) {                                        // Función que carga la información del juego en la parte superior de la pantalla
-        for i in 1..19 {
-            self.map.map[0][i as usize] = 0;
+        let width = self.view_width as usize;
+        let heart = self.map.hud_tile(HudRole::Heart).unwrap_or(0);    // Un mapa sin los sprites del HUD simplemente no los muestra
+        let hud = &mut self.hud;
+        for tile in hud.iter_mut() {
+            *tile = 0;
+        }
+        for i in 0..self.lifes as usize {
+            if i + 1 < width.saturating_sub(1) {
+                hud[i + 1] = heart;
+            }
+        }
+        let s_score = format!("{:>04}", self.score);
+        for (i, digit) in self.map.digit_tiles(&s_score).into_iter().enumerate() {
+            if let Some(tile) = (width + i).checked_sub(5).and_then(|x| hud.get_mut(x)) {
+                *tile = digit;
+            }
+        }
+        let duration = self.campaign.levels[self.level].duration;
+        if duration > 0 {                                               // Segundos restantes del nivel al centro
+            let seconds = duration.saturating_sub(self.level_tick) * TICK_MS / 1000;
+            for (i, digit) in self.map.digit_tiles(&format!("{:>03}", seconds.min(999))).into_iter().enumerate() {
+                if let Some(tile) = (width / 2 + i).checked_sub(1).and_then(|x| hud.get_mut(x)) {
+                    *tile = digit;
+                }
+            }
+        }
+
+        Character::write_digits(&mut self.map_end, 0, &s_score);       // El puntaje final va centrado en la fila superior
+        Character::write_digits(&mut self.map_victory, 0, &s_score);
+        let middle = self.map_level.height() / 2;                       // El número de nivel va centrado en la pantalla
+        Character::write_digits(&mut self.map_level, middle, &format!("{:>02}", (self.level + 2).min(99)));
+    }
+
+    fn write_digits(map: &mut GameMap, y: u32, text: &str) {            // Función que escribe un número centrado en la fila y de una pantalla
+        let digits = map.digit_tiles(text);
+        let x = (map.width() as usize).saturating_sub(digits.len()) / 2;
+        if let Some(row) = map.map.get_mut(y as usize) {
+            for (tile, digit) in row.iter_mut().skip(x).zip(digits) {
+                *tile = digit;
+            }
+        }
+    }
+
+    pub fn spawn_coord(width: u32, height: u32) -> Coord {                 // Función que indica dónde aparece el zorro: al centro de la penúltima fila
+        Coord((width / 2).saturating_sub(1), height.saturating_sub(2))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn view_width(&self) -> u32 {
+        self.view_width
+    }
+
+    pub fn assets(&self) -> &Assets {
+        &self.assets
+    }
+                                                                        // Función que cambia los sprites y los mapas por los de assets sin reiniciar la partida
+    pub fn reload(&mut self, assets: Assets) -> Result<(), String> {
+        let mut levels = self.campaign.levels.clone();
+        for level in levels.iter_mut() {                                // Sólo cambian los niveles que vienen de un archivo
+            if let Some((file, text)) = level.file.as_ref().and_then(|file| assets.get(file).map(|text| (file, text))) {
+                level.map = GameMap::from_text(String::from(text)).map_err(|(line, reason)| format!("{}:{}: {}", file, line, reason))?;
+            }
+            if level.map.height() != self.height || level.map.view_width() != self.view_width {
+                return Err(format!("el mapa {} no tiene una pantalla de {}x{}", level.name, self.view_width, self.height));
+            }
+        }
+        let mut map = levels[self.level].map.clone();
+        let original = &self.campaign.levels[self.level].map;
+        if map.width() == original.width() {                            // Se conservan las celdas que cambiaron en la partida (como los MedKit ya usados)
+            for (y, row) in map.map.iter_mut().enumerate() {
+                for (x, tile) in row.iter_mut().enumerate() {
+                    if self.map.map[y][x] != original.map[y][x] {
+                        *tile = self.map.map[y][x];
+                    }
+                }
+            }
+        }
+        self.campaign.levels = levels;
+        self.map = map;
+        self.width = self.map.width();
+        self.coord.0 = self.coord.0.min(self.width - 1);
+
+        self.sprites = Character::load_sprites(&assets);
+        (self.lava_ball, self.apple) = Character::projectile_sprites(&assets);
+        if self.last_dir == Direction::Left {                           // Los sprites nuevos miran a la derecha
+            self.invert_side();
+        }
+
+        let (view_width, height) = (self.view_width, self.height);
+        self.map_start = Character::screen_map(&assets, "mapStart.txt").centered(view_width, height);
+        self.map_end = Character::screen_map(&assets, "mapEnd.txt").centered(view_width, height);
+        self.map_pause = Character::screen_map(&assets, "mapPause.txt").centered(view_width, height);
+        self.map_level = Character::screen_map(&assets, "mapLevel.txt").centered(view_width, height);
+        self.map_victory = Character::screen_map(&assets, "mapVictory.txt").centered(view_width, height);
+        self.themes = Theme::from_assets(&assets);
+        if self.theme >= self.themes.len() {
+            self.theme = 0;
+        }
+        self.assets = assets;
+        self.recolor();
+
+        if self.game_state != GameState::Start {
+            self.load_hud();
+        }
+        Ok(())
+    }
+
+    pub fn theme(&self) -> &Theme {                                     // Función que regresa el tema de colores actual
+        &self.themes[self.theme]
+    }
+
+    pub fn themes(&self) -> &[Theme] {
+        &self.themes
+    }
+
+    pub fn set_theme(&mut self, theme: usize) {                         // Función que cambia el tema de colores sin reiniciar la partida
+        if theme < self.themes.len() {
+            self.theme = theme;
+            self.recolor();
+        }
+    }
+
+    pub fn next_theme(&mut self) {                                      // Función que pasa al siguiente tema, sólo desde la pantalla de inicio
+        if self.game_state == GameState::Start {
+            self.set_theme((self.theme + 1) % self.themes.len());
+        }
+    }
+
+    fn recolor(&mut self) {                                             // Función que vuelve a pintar sprites, mapas y proyectiles con el tema actual
+        let theme = &self.themes[self.theme];
+        let original = Character::load_sprites(&self.assets);
+        for (list, orig) in self.sprites.iter_mut().zip(original.iter()) {
+            for (spte, orig) in list.iter_mut().zip(orig.iter()) {
+                theme.apply_sprite(spte, orig);
+            }
         }
-        for i in 0..self.lifes {
-            self.map.map[0][i as usize + 1 as usize] = 5;
+
+        theme.apply_map(&mut self.map, &self.campaign.levels[self.level].map);
+        for (map, file) in [
+            (&mut self.map_start, "mapStart.txt"),
+            (&mut self.map_end, "mapEnd.txt"),
+            (&mut self.map_pause, "mapPause.txt"),
+            (&mut self.map_level, "mapLevel.txt"),
+            (&mut self.map_victory, "mapVictory.txt"),
+        ] {
+            theme.apply_map(map, &Character::screen_map(&self.assets, file));
+        }
+
+        for proj in self.ices.iter_mut() {
+            theme.apply_sprite(&mut proj.spte, &self.lava_ball);
         }
-        let s_score = String::from( format!("{:>04}", self.score) );
-        for (i, c) in s_score.chars().enumerate() {
-            self.map.map[0][15 as usize + i as usize] = (c as u8 - '0' as u8) + 8;
-            self.map_end.map[0][8 as usize + i as usize] = (c as u8 - '0' as u8) + 26;
+        for proj in self.apples.iter_mut() {
+            theme.apply_sprite(&mut proj.spte, &self.apple);
         }
     }
 }
 
 impl Character {
     pub fn arrow_left_down(&mut self) {                                 // Función que indica que la flecha IZQUIERDA se presionó
-        if let State::Stand = self.get_state() {
-            self.set_state(State::Walk(Direction::Left));
-        } else if let State::Jump(_d) = self.get_state() {
-            self.set_state(State::Jump(Direction::Left));
-        } else if let State::Fall(_d) = self.get_state() {
-            self.set_state(State::Fall(Direction::Left));
-        } else if let State::Walk(_d) = self.get_state() {
-            self.set_state(State::Walk(Direction::Left));
-        }
+        self.side_key_down(Direction::Left);
     }
 
     pub fn arrow_right_down(&mut self) {                                // Función que indica que la flecha DERECHA se presionó
-        if self.get_state() == State::Stand {
-            self.set_state(State::Walk(Direction::Right));
-        } else if let State::Jump(_d) = self.get_state() {
-            self.set_state(State::Jump(Direction::Right));
-        } else if let State::Fall(_d) = self.get_state() {
-            self.set_state(State::Fall(Direction::Right));
-        } else if let State::Walk(_d) = self.get_state() {
-            self.set_state(State::Walk(Direction::Right));
-        }
+        self.side_key_down(Direction::Right);
     }
 
     pub fn arrow_down_down(&mut self) {                                 // Función que indica que la flecha ABAJO se presionó
         self.down_key = true;
-        if self.get_state() == State::Stand {
-            self.set_state(State::Crouch);
-        } else if let State::Jump(_d) = self.get_state() {
-            self.set_state(State::Meteor);
-        } else if let State::Fall(_d) = self.get_state() {
-            self.set_state(State::Meteor);
-        }
+        self.fire(Event::DownKeyDown, Direction::None);
     }
 
     pub fn arrow_up_down(&mut self) {                                   // Función que indica que la flecha ARRIBA se presionó
-        if self.get_state() == State::Stand {
-            self.set_state(State::Jump(Direction::None));
-        } else if let State::Walk(dir) = self.get_state() {
-            self.set_state(State::Jump( dir ));
+        self.up_key = true;
+        if self.fire(Event::UpKeyDown, self.state.dir()) {              // Al saltar caminando se conserva la dirección
+            self.vel_y = -JUMP_SPEED;
         }
     }
 
-    pub fn arrow_left_up(&mut self) {                                   // Función que indica que la flecha IZQUIERDA se liberó
-        if let State::Walk(dir) = self.get_state() {
-            if dir == Direction::Left {
-                self.set_state(State::Stand);
-            }
-        } else if let State::Jump(dir) = self.get_state() {
-            if dir == Direction::Left {
-                self.set_state(State::Jump(Direction::None));
-            }
-        } else if let State::Fall(dir) = self.get_state() {
-            if dir == Direction::Left {
-                self.set_state(State::Fall(Direction::None));
-            }
+    pub fn arrow_up_up(&mut self) {                                     // Función que indica que la flecha ARRIBA se liberó, lo que recorta el salto
+        self.up_key = false;
+        if let State::Jump(_) = self.state {
+            self.vel_y = self.vel_y.max(-CUT_SPEED);
         }
     }
 
+    pub fn arrow_left_up(&mut self) {                                   // Función que indica que la flecha IZQUIERDA se liberó
+        self.side_key_up(Direction::Left);
+    }
+
     pub fn arrow_right_up(&mut self) {                                  // Función que indica que la flecha DERECHA se liberó
-        if let State::Walk(dir) = self.get_state() {
-            if dir == Direction::Right {
-                self.set_state(State::Stand);
-            }
-        } else if let State::Jump(dir) = self.get_state() {
-            if dir == Direction::Right {
-                self.set_state(State::Jump(Direction::None));
-            }
-        } else if let State::Fall(dir) = self.get_state() {
-            if dir == Direction::Right {
-                self.set_state(State::Fall(Direction::None));
-            }
-        }
+        self.side_key_up(Direction::Right);
     }
 
     pub fn arrow_down_up(&mut self) {                                   // Función que indica que la tecla ABAJO se liberó
         self.down_key=false;
-        if self.get_state() == State::Crouch {
-            self.set_state(State::Stand);
+        self.fire(Event::DownKeyUp, Direction::None);
+    }
+
+    fn side_key_down(&mut self, dir: Direction) {                       // Función que empieza a caminar o cambia la dirección de Walk, Jump y Fall
+        if !self.fire(Event::SideKeyDown, dir) && self.state.has_dir() {
+            self.steer(dir);
+        }
+    }
+
+    fn side_key_up(&mut self, dir: Direction) {                         // Función que deja de caminar o de moverse en el aire si se soltó la flecha de la dirección actual
+        if self.state.dir() == dir && !self.fire(Event::SideKeyUp, Direction::None) {
+            self.steer(Direction::None);
+        }
+    }
+
+    pub fn apply_input(&mut self, input: Input) {                       // Función que aplica un evento del teclado como lo hace la interfaz web
+        if self.game_state == GameState::Paused {
+            match input {                                               // En pausa sólo se registran las teclas
+                Input::LeftDown | Input::LeftUp => self.set_left_key(input == Input::LeftDown),
+                Input::RightDown | Input::RightUp => self.set_right_key(input == Input::RightDown),
+                Input::DownDown | Input::DownUp => self.down_key = input == Input::DownDown,
+                Input::UpUp => self.up_key = false,
+                Input::Pause => self.resume(),
+                _ => {},
+            }
+            return
+        }
+        match input {
+            Input::LeftDown | Input::RightDown | Input::DownDown | Input::UpDown
+                if self.game_state != GameState::Play => {},              // Las teclas presionadas sólo cuentan mientras se juega
+            Input::LeftDown => {
+                self.set_left_key(true);
+                self.arrow_left_down();
+            },
+            Input::RightDown => {
+                self.set_right_key(true);
+                self.arrow_right_down();
+            },
+            Input::DownDown => self.arrow_down_down(),
+            Input::UpDown => self.arrow_up_down(),
+            Input::LeftUp => {
+                self.set_left_key(false);
+                self.arrow_left_up();
+            },
+            Input::RightUp => {
+                self.set_right_key(false);
+                self.arrow_right_up();
+            },
+            Input::DownUp => self.arrow_down_up(),
+            Input::UpUp => self.arrow_up_up(),
+            Input::Start => self.start(),
+            Input::Pause => self.pause(),
+        }
+    }
+
+    pub fn pause(&mut self) {                                           // Función que pausa el juego guardando las teclas presionadas
+        if self.game_state == GameState::Play {
+            self.game_state = GameState::Paused;
+            self.paused_keys = (self.left_key, self.right_key, self.down_key);
+        }
+    }
+
+    pub fn resume(&mut self) {                                          // Función que reanuda el juego aplicando las teclas que cambiaron durante la pausa
+        if self.game_state != GameState::Paused {
+            return
+        }
+        self.game_state = GameState::Play;
+        let (left, right, down) = self.paused_keys;
+        if self.left_key != left {
+            if self.left_key { self.arrow_left_down() } else { self.arrow_left_up() }
+        }
+        if self.right_key != right {
+            if self.right_key { self.arrow_right_down() } else { self.arrow_right_up() }
+        }
+        if self.down_key != down {
+            if self.down_key { self.arrow_down_down() } else { self.arrow_down_up() }
+        }
+        if !self.up_key {                                               // Si se soltó ARRIBA durante la pausa se recorta el salto
+            self.arrow_up_up();
         }
     }
 
@@ -612,32 +1102,288 @@ impl Character {
         if self.game_state == GameState::Start {
             self.game_state = GameState::Play;
 
-            self.state = State::Stand;
-            self.last_state = State::Stand;
+            self.state = self.initial_state();
+            self.last_state = self.state;
         }
-        if self.game_state == GameState::GameOver {
-            if self.dir == Direction::Left {
-                self.invert_side();
-            }
+        if self.game_state == GameState::GameOver || self.game_state == GameState::Victory {
             self.game_state = GameState::Play;
 
-            self.coord = Coord(9, 8);
+            self.score = 0;
+            if let Some(params) = self.campaign.arena {                 // En el modo sin fin cada partida tiene una arena nueva
+                self.campaign = Campaign::endless(self.random.next_u64(), &params, &self.assets);
+            }
+            self.load_level(0);
 
-            self.state = State::Stand;
-            self.last_state = State::Stand;
-            self.stt_ix = 0;
+            self.lifes = 2;
+        }
+        if self.game_state == GameState::LevelComplete {
+            self.next_level();
+        }
+    }
+}
 
-            self.dir = Direction::Right;
-            self.last_dir = Direction::Right;
+impl Character {
+    pub fn snapshot(&self) -> Snapshot {                                // Función que genera una fotografía completa del estado del juego
+        let mut projectiles = Vec::new();
+        for (kind, list) in [(ProjectileKind::Ice, &self.ices), (ProjectileKind::Apple, &self.apples)].iter() {
+            for proj in list.iter() {
+                let (x_max, y_max) = proj.bounds();
+                projectiles.push(ProjectileSnapshot {
+                    kind: *kind,
+                    x: proj.coord.x,
+                    y: proj.coord.y,
+                    dir: proj.coord.dir,
+                    x_max,
+                    y_max,
+                    bitmap: proj.spte.map.clone(),
+                });
+            }
+        }
 
-            self.jump_h = 0;
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            width: self.width,
+            height: self.height,
+            game_state: self.game_state,
+            campaign: self.campaign.source.clone(),
+            level: self.level,
+            level_tick: self.level_tick,
+            level_score: self.level_score,
+            state: self.state,
+            last_state: self.last_state,
+            coord: (self.coord.0, self.coord.1),
+            dir: self.dir,
+            last_dir: self.last_dir,
+            it: self.it,
+            tick: self.tick,
+            game_over_tick: self.game_over_tick,
+            motion: Some((self.pos_x, self.pos_y, self.vel_y, self.up_key)),
+            left_key: self.left_key,
+            right_key: self.right_key,
+            down_key: self.down_key,
+            paused_keys: self.paused_keys,
+            score: self.score,
+            lifes: self.lifes,
+            random: self.random.get_state(),
+            projectiles,
+            map_start: Character::map_snapshot(&self.map_start),
+            map: Character::map_snapshot(&self.map),
+            map_end: Character::map_snapshot(&self.map_end),
+            theme: self.theme().name.clone(),
+        }
+    }
+                                                                        // Función que reconstruye al jugador a partir de una fotografía
+    pub fn from_snapshot(snapshot: &Snapshot) -> Result<Character, String> {
+        Character::from_snapshot_with(snapshot, Assets::embedded())
+    }
+                                                                        // Igual que from_snapshot, con los niveles y sprites de assets
+    pub fn from_snapshot_with(snapshot: &Snapshot, assets: Assets) -> Result<Character, String> {
+        let random: Box<dyn RandomSource> = match snapshot.random {
+            Some(state) => Box::new(XorShift::from_state(state)),
+            None => Box::new(XorShift::new(snapshot.tick)),
+        };
+        let campaign = Campaign::from_source(&snapshot.campaign, &assets)?;  // La partida se arma igual que cuando se guardó
+        let mut character = Character::with_assets(campaign, assets, random);
+        if snapshot.level >= character.campaign.levels.len() {
+            return Err(format!("nivel fuera de rango {}", snapshot.level));
+        }
+        character.load_level(snapshot.level);
+        if snapshot.width != character.width || snapshot.height != character.height {
+            return Err(format!("el tamaño {}x{} no coincide con el del mapa", snapshot.width, snapshot.height));
+        }
 
-            self.ices.clear();
-            self.apples.clear();
+        if snapshot.coord.0 >= snapshot.width || snapshot.coord.1 >= snapshot.height.saturating_sub(1) {
+            return Err(format!("coordenadas fuera del mapa {:?}", snapshot.coord));
+        }
+        if snapshot.it >= character.max_it {
+            return Err(format!("iteración fuera de rango {}", snapshot.it));
+        }
 
-            self.score = 0;
+        Character::restore_map(&mut character.map_start, &snapshot.map_start, "Start")?;
+        let top = character.map.map.first().cloned();
+        Character::restore_map(&mut character.map, &snapshot.map, "Play")?;
+        if let (true, Some(top)) = (snapshot.version < 3, top) {        // La fila superior se borraba para el HUD, se toma del nivel
+            character.map.map[0] = top;
+        }
+        Character::restore_map(&mut character.map_end, &snapshot.map_end, "End")?;
+
+        for proj in snapshot.projectiles.iter() {
+            let mut spte = match proj.kind {
+                ProjectileKind::Ice => character.lava_ball.deref(),
+                ProjectileKind::Apple => character.apple.deref(),
+            };
+            spte.map = proj.bitmap.clone();
+            if proj.x_max != character.width as i32 || proj.y_max != character.height as i32 {
+                return Err(format!("el proyectil tiene límites {}x{} y el mapa mide {}x{}", proj.x_max, proj.y_max, character.width, character.height));
+            }
+            if !(0..=proj.x_max * UNITS).contains(&proj.x) || !(0..=proj.y_max * UNITS).contains(&proj.y) {
+                return Err(format!("proyectil fuera del mapa {} {}", proj.x, proj.y));
+            }
+            let coord = ProjectileCoord {
+                x: proj.x,
+                y: proj.y,
+                dir: proj.dir,
+            };
+            let restored = Projectile::from_parts(&spte, coord, proj.x_max, proj.y_max);
+            match proj.kind {
+                ProjectileKind::Ice => character.ices.push(restored),
+                ProjectileKind::Apple => character.apples.push(restored),
+            }
+        }
 
-            self.lifes = 2;
+        character.change_dir(snapshot.last_dir);                        // Los sprites del jugador se orientan según la última dirección
+        character.set_state(snapshot.state);
+        character.last_state = snapshot.last_state;
+        character.game_state = snapshot.game_state;
+        character.coord = Coord(snapshot.coord.0, snapshot.coord.1);
+        character.dir = snapshot.dir;
+        character.it = snapshot.it;
+        character.tick = snapshot.tick;
+        character.game_over_tick = snapshot.game_over_tick;
+        let (x, y) = (snapshot.coord.0 as i32 * UNITS, snapshot.coord.1 as i32 * UNITS);
+        let (pos_x, pos_y, vel_y, up_key) = snapshot.motion.unwrap_or((x, y, 0, false));
+        let world = (snapshot.width as i32 * UNITS, snapshot.height as i32 * UNITS);
+        if !(0..world.0).contains(&pos_x) || !(0..world.1).contains(&pos_y) {   // Se revisa antes de calcular la celda para no desbordar
+            return Err(format!("posición fuera del mapa {} {}", pos_x, pos_y));
+        }
+        if vel_y.abs() > JUMP_SPEED.max(METEOR_SPEED) {
+            return Err(format!("velocidad vertical fuera de rango {}", vel_y));
+        }
+        if cell(pos_x) % snapshot.width != snapshot.coord.0 {
+            return Err(format!("la posición {} no corresponde a la columna {}", pos_x, snapshot.coord.0));
+        }
+        if cell(pos_y) != snapshot.coord.1 {
+            return Err(format!("la altura {} no corresponde a la fila {}", pos_y, snapshot.coord.1));
+        }
+        character.pos_x = pos_x;
+        character.pos_y = pos_y;
+        character.last_pos = (pos_x, pos_y);
+        character.vel_y = vel_y;
+        character.up_key = up_key;
+        character.left_key = snapshot.left_key;
+        character.right_key = snapshot.right_key;
+        character.down_key = snapshot.down_key;
+        character.paused_keys = snapshot.paused_keys;
+        character.score = snapshot.score;
+        character.lifes = snapshot.lifes;
+        character.level_tick = snapshot.level_tick;
+        character.level_score = snapshot.level_score;
+        if character.game_state != GameState::Start {                   // Las pantallas de nivel y de victoria no se guardan, se vuelven a escribir
+            character.load_hud();
+        }
+        if let Some(theme) = character.themes.iter().position(|t| t.name == snapshot.theme) {
+            character.set_theme(theme);                                 // Un tema que ya no existe se cambia por el Clásico
+        }
+
+        Ok(character)
+    }
+
+    fn map_snapshot(map: &GameMap) -> MapSnapshot {                     // Función que copia las celdas y los mapas de bits de un mapa
+        MapSnapshot {
+            tiles: map.map.iter().map(|row| row.to_vec()).collect(),
+            bitmaps: map.sprites.iter().map(|spte| spte.map.clone()).collect(),
+        }
+    }
+                                                                        // Función que restaura un mapa comprobando que coincida con el mapa cargado
+    fn restore_map(map: &mut GameMap, snapshot: &MapSnapshot, name: &str) -> Result<(), String> {
+        if snapshot.tiles.len() != map.map.len() || snapshot.tiles.iter().any(|row| row.len() != map.width() as usize) {
+            return Err(format!("el mapa {} no tiene {}x{} celdas", name, map.width(), map.height()));
+        }
+        if snapshot.bitmaps.len() != map.sprites.len() {
+            return Err(format!("el mapa {} debe tener {} sprites", name, map.sprites.len()));
+        }
+        for (i, row) in snapshot.tiles.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                if *tile as usize > map.sprites.len() {
+                    return Err(format!("el mapa {} usa el sprite {} que no existe", name, tile));
+                }
+                map.map[i][j] = *tile;
+            }
+        }
+        for (spte, bitmap) in map.sprites.iter_mut().zip(snapshot.bitmaps.iter()) {
+            spte.map = bitmap.clone();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playing(seed: u64) -> Character {                                // Partida recién iniciada con los niveles por defecto
+        let mut character = Character::new(seed);
+        character.apply_input(Input::Start);
+        character
+    }
+
+    #[test]
+    fn waits_on_the_start_screen() {
+        let mut character = Character::new(1);
+        for _ in 0..50 {
+            character.update();
+        }
+        assert_eq!(character.game_state, GameState::Start);
+        assert_eq!(character.get_state(), State::Stand);
+        assert!(character.get_ices().is_empty());
+    }
+
+    #[test]
+    fn walks_while_the_arrow_is_held() {
+        let mut character = playing(1);
+        let start = character.get_coord();
+        character.apply_input(Input::RightDown);
+        for _ in 0..4 {
+            character.update();
+        }
+        assert_eq!(character.get_state(), State::Walk(Direction::Right));
+        assert_eq!(character.get_coord(), Coord(start.0 + 2, start.1));
+
+        character.apply_input(Input::RightUp);
+        character.update();
+        assert_eq!(character.get_state(), State::Stand);
+    }
+
+    #[test]
+    fn jumps_and_lands_on_the_same_row() {
+        let mut character = playing(1);
+        let start = character.get_coord();
+        character.apply_input(Input::UpDown);
+        let mut states = vec![character.get_state().name()];
+        for _ in 0..100 {
+            character.update();
+            if states.last() != Some(&character.get_state().name()) {
+                states.push(character.get_state().name());
+            }
+            if character.get_state() == State::Stand {
+                break;
+            }
+        }
+        assert_eq!(states, ["Jump", "Fall", "Stand"]);
+        assert_eq!(character.get_coord(), start);
+    }
+
+    #[test]
+    fn game_ends_when_the_lifes_run_out() {
+        let mut character = playing(7);
+        while character.get_game_over_tick().is_none() && character.get_tick() < 10000 {
+            character.update();
+        }
+        assert_eq!(character.game_state, GameState::GameOver);
+        assert_eq!(character.get_game_over_tick(), Some(character.get_tick()));
+        assert!(character.transition_hits().iter().sum::<u32>() > 0);
+    }
+
+    #[test]
+    fn reload_changes_the_projectile_sprites() {
+        let mut character = playing(3);
+        let mut assets = Assets::embedded();
+        let text = assets.text("fox_sprites.txt").replacen("LavaBall:black-", "LavaBall:white-", 1);
+        assert!(assets.insert("fox_sprites.txt", text).is_empty());
+        character.reload(assets).unwrap();
+        while character.get_ices().is_empty() {
+            character.update();
         }
+        assert_eq!(character.get_ices()[0].spte.colorset[0], "white");
     }
 }
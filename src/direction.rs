@@ -2,7 +2,7 @@
  * Enumeración que indica las posibles direcciones.
  */
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum Direction {
     Left,
     Right,
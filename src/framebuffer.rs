@@ -0,0 +1,310 @@
+/*
+ * Implementación del trait Renderer sobre un buffer RGBA en memoria. Cada celda del mapa ocupa 8x8 pixeles
+ * del sprite, y cada pixel del sprite se escala al número de pixeles que se indique con scale, de modo que
+ * un mapa de 20x10 con scale = 5 genera una imagen de 800x400 (el mismo tamaño que el canvas del navegador).
+ *
+ * Los colores de los sprites y del fondo se leen como en el canvas (nombres de CSS o hexadecimales) y se
+ * convierten con el módulo color; si un color no se reconoce se usa negro. El pixel 0 de un sprite es
+ * transparente y no modifica el buffer.
+ *
+ * No se cuenta con una fuente para dibujar letras, por lo que draw_text sólo guarda el texto junto con su
+ * posición en la lista texts, así se puede comprobar qué se escribió. La máquina de estados se dibuja con
+ * las posiciones de StateGraph escaladas al tamaño del buffer: los estados son círculos (elipses si el
+ * buffer no es cuadrado), las transiciones líneas con las alas de la flecha y los nombres, las etiquetas y
+ * el historial quedan en texts como los demás textos.
+ *
+ * Un cuadro se puede guardar como PPM (formato P6, sin canal alfa) o como PNG, lo que permite generar
+ * capturas de pantalla, miniaturas de los niveles o imágenes de referencia para pruebas sin un navegador.
+ * Las pruebas de este módulo comparan algunas pantallas del juego con las imágenes de tests/golden; si un
+ * cambio en los mapas o los sprites es intencional, se vuelven a generar con FOX_HELL_BLESS=1 cargo test.
+ */
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::color::{parse_color, Rgba};
+use crate::graph::{StateGraph, NODE_RADIUS};
+use crate::sprite::Sprite;
+use crate::renderer::Renderer;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawnText {
+    pub x: f64,
+    pub y: f64,
+    pub text: String,
+    pub color: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct FrameBuffer {
+    width: u32,
+    height: u32,
+    scale: u32,
+    pixels: Vec<u8>,
+    pub texts: Vec<DrawnText>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: u32, height: u32, scale: u32) -> FrameBuffer {        // width y height en celdas del mapa
+        let scale = scale.max(1);
+        let size = (width * 8 * scale) as usize * (height * 8 * scale) as usize * 4;
+        FrameBuffer {
+            width,
+            height,
+            scale,
+            pixels: vec![0; size],
+            texts: Vec::new(),
+        }
+    }
+
+    pub fn pixel_width(&self) -> u32 {
+        self.width * 8 * self.scale
+    }
+
+    pub fn pixel_height(&self) -> u32 {
+        self.height * 8 * self.scale
+    }
+
+    pub fn pixels(&self) -> &[u8] {                                         // Buffer RGBA, fila por fila
+        &self.pixels
+    }
+
+    pub fn get_pixel(&self, x: u32, y: u32) -> Rgba {
+        let i = ((y * self.pixel_width() + x) * 4) as usize;
+        [self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]]
+    }
+
+    pub fn fill_rect(&mut self, x: i64, y: i64, width: i64, height: i64, color: Rgba) {
+        let x0 = x.max(0);
+        let y0 = y.max(0);
+        let x1 = (x + width).min(self.pixel_width() as i64);
+        let y1 = (y + height).min(self.pixel_height() as i64);
+        for py in y0..y1 {
+            for px in x0..x1 {
+                let i = ((py * self.pixel_width() as i64 + px) * 4) as usize;
+                self.pixels[i..i + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.pixel_width(), self.pixel_height())?;
+        for pixel in self.pixels.chunks(4) {
+            out.write_all(&pixel[..3])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_png<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, self.pixel_width(), self.pixel_height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(to_io_error)?;
+        writer.write_image_data(&self.pixels).map_err(to_io_error)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {         // Guarda el cuadro como PNG o PPM según la extensión del archivo
+        let path = path.as_ref();
+        let mut out = BufWriter::new(File::create(path)?);
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ppm") => self.write_ppm(&mut out)?,
+            _ => self.write_png(&mut out)?,
+        }
+        out.flush()
+    }
+
+    fn graph_line(&mut self, from: (f64, f64), to: (f64, f64), size: i64, color: Rgba) { // Línea del status con posiciones normalizadas
+        let (w, h) = (self.pixel_width() as f64, self.pixel_height() as f64);
+        let (x0, y0, x1, y1) = (from.0 * w, from.1 * h, to.0 * w, to.1 * h);
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as i64;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            self.fill_rect(x as i64 - size / 2, y as i64 - size / 2, size, size, color);
+        }
+    }
+
+    fn graph_node(&mut self, x: f64, y: f64, color: Rgba) {                  // Elipse de un estado del status con posición normalizada
+        let (w, h) = (self.pixel_width() as f64, self.pixel_height() as f64);
+        let (cx, cy, rx, ry) = (x * w, y * h, NODE_RADIUS * w, NODE_RADIUS * h);
+        for py in (cy - ry).floor() as i64..=(cy + ry).ceil() as i64 {
+            let dy = (py as f64 + 0.5 - cy) / ry;
+            if dy.abs() > 1.0 {
+                continue;
+            }
+            let half = rx * (1.0 - dy * dy).sqrt();
+            let x0 = (cx - half).round() as i64;
+            self.fill_rect(x0, py, (cx + half).round() as i64 - x0, 1, color);
+        }
+    }
+}
+
+impl Renderer for FrameBuffer {
+    fn clear(&mut self, bg: &str) {
+        let color = to_rgba(bg);
+        for pixel in self.pixels.chunks_mut(4) {
+            pixel.copy_from_slice(&color);
+        }
+        self.texts.clear();
+    }
+
+    fn draw_sprite(&mut self, x: f64, y: f64, spte: &Sprite) {
+        let cell = (8 * self.scale) as f64;
+        let left = (x * cell).round() as i64;                               // Las fracciones de celda se redondean al pixel más cercano
+        let top = ((y + 1.0) * cell).round() as i64 - spte.map.height() as i64 * self.scale as i64; // Los sprites altos crecen hacia arriba
+        for (i, row) in spte.map.0.iter().enumerate() {
+            for (j, pixel) in row.iter().enumerate() {
+                let color = match spte.colorset.get((*pixel as usize).wrapping_sub(1)) {
+                    Some(color) => to_rgba(color),
+                    None => continue,                                       // El 0 es transparente, igual que un color que no existe
+                };
+                self.fill_rect(
+                    left + j as i64 * self.scale as i64,
+                    top + i as i64 * self.scale as i64,
+                    self.scale as i64,
+                    self.scale as i64,
+                    color
+                );
+            }
+        }
+    }
+
+    fn draw_text(&mut self, x: f64, y: f64, text: &str, color: &str) {
+        self.texts.push(DrawnText {
+            x,
+            y,
+            text: text.to_string(),
+            color: color.to_string(),
+        });
+    }
+
+    fn draw_graph(&mut self, graph: &StateGraph) {
+        self.clear("white");
+        let (width, height) = (self.width as f64, self.height as f64);     // Los textos van en celdas
+
+        for (i, edge) in graph.edges.iter().enumerate() {
+            let (size, color) = match graph.last_edge == Some(i) {
+                true => (3 * self.scale as i64, to_rgba("limegreen")),
+                false => (self.scale as i64, to_rgba("gray")),
+            };
+            let path = graph.edge_path(i);
+            for pair in path.windows(2) {
+                self.graph_line(pair[0], pair[1], size, color);
+            }
+            let [tip, left, right] = graph.arrow_head(i);
+            self.graph_line(tip, left, size, color);
+            self.graph_line(tip, right, size, color);
+            let (x, y) = graph.label_pos(i);
+            self.draw_text(x * width, y * height, &edge.label, "black");
+        }
+
+        for (i, node) in graph.nodes.iter().enumerate() {
+            let color = if graph.active == Some(i) { "limegreen" } else { "dodgerblue" };
+            self.graph_node(node.x, node.y, to_rgba(color));
+            self.draw_text(node.x * width, node.y * height, &node.label, "black");
+        }
+
+        for (line, text) in graph.history.iter().enumerate() {
+            let (x, y) = graph.history_pos(line);
+            self.draw_text(x * width, y * height, text, "black");
+        }
+    }
+}
+
+fn to_rgba(color: &str) -> Rgba {
+    parse_color(color).unwrap_or([0, 0, 0, 255])
+}
+
+fn to_io_error(err: png::EncodingError) -> io::Error {
+    io::Error::other(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::path::PathBuf;
+
+    use crate::bitmap::BitMap;
+    use crate::character::Character;
+    use crate::input::Input;
+
+    fn golden(name: &str) -> PathBuf {                                      // Las imágenes de referencia se guardan con FOX_HELL_BLESS=1
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name)
+    }
+
+    fn check_golden(frame: &FrameBuffer, name: &str) {
+        let path = golden(name);
+        if env::var_os("FOX_HELL_BLESS").is_some() {
+            frame.save(&path).unwrap();
+            return;
+        }
+        let file = File::open(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        let mut reader = png::Decoder::new(file).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (frame.pixel_width(), frame.pixel_height()), "tamaño de {}", name);
+        let differ = pixels.chunks(4).zip(frame.pixels().chunks(4)).filter(|(a, b)| a != b).count();
+        assert_eq!(differ, 0, "{} pixeles distintos de {}", differ, name);
+    }
+
+    fn frame_of(character: &Character) -> FrameBuffer {
+        let mut frame = FrameBuffer::new(character.view_width(), character.height(), 1);
+        character.draw(&mut frame);
+        frame
+    }
+
+    #[test]
+    fn draws_sprite_pixels_scaled() {
+        let mut map = BitMap::new();
+        map.0[0][0] = 1;
+        map.0[7][7] = 2;
+        let spte = Sprite::with_map(vec![String::from("red"), String::from("#0000ff")], map);
+        let mut frame = FrameBuffer::new(2, 1, 2);
+        frame.clear("white");
+        frame.draw_sprite(1.0, 0.0, &spte);
+
+        assert_eq!(frame.get_pixel(16, 0), [255, 0, 0, 255]);
+        assert_eq!(frame.get_pixel(17, 1), [255, 0, 0, 255]);
+        assert_eq!(frame.get_pixel(31, 15), [0, 0, 255, 255]);
+        assert_eq!(frame.get_pixel(18, 0), [255, 255, 255, 255]);           // El 0 es transparente
+        assert_eq!(frame.get_pixel(15, 0), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn clips_sprites_outside_the_buffer() {
+        let spte = Sprite::with_map(vec![String::from("red")], BitMap(vec![vec![1; 8]; 8]));
+        let mut frame = FrameBuffer::new(1, 1, 1);
+        frame.clear("black");
+        frame.draw_sprite(-0.5, 0.5, &spte);
+
+        assert_eq!(frame.get_pixel(3, 4), [255, 0, 0, 255]);
+        assert_eq!(frame.get_pixel(4, 4), [0, 0, 0, 255]);
+        assert_eq!(frame.get_pixel(3, 3), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn start_screen_matches_golden() {
+        check_golden(&frame_of(&Character::new(1)), "start.png");
+    }
+
+    #[test]
+    fn first_frame_matches_golden() {
+        let mut character = Character::new(1);
+        character.apply_input(Input::Start);
+        character.update();
+        check_golden(&frame_of(&character), "play.png");
+    }
+
+    #[test]
+    fn pause_screen_matches_golden() {
+        let mut character = Character::new(1);
+        character.apply_input(Input::Start);
+        for _ in 0..10 {
+            character.update();
+        }
+        character.apply_input(Input::Pause);
+        check_golden(&frame_of(&character), "pause.png");
+    }
+}
@@ -0,0 +1,386 @@
+/*
+ * Máquina de estados del jugador. Las transiciones no se escriben en el código sino que se leen del diagrama
+ * StateMachine_FoxHell.jff (el formato XML de JFLAP, incluido en el binario), así el diagrama y el juego no
+ * pueden dejar de coincidir: cada estado del diagrama tiene como etiqueta el nombre de un estado del jugador
+ * (ver state.rs) y cada transición lee el nombre de un evento de Event.
+ *
+ * Character aplica los eventos con find: si el diagrama no tiene una transición para el estado actual y el
+ * evento, ésta se rechaza y el estado no cambia. La dirección de Walk, Jump y Fall es un sub-estado y
+ * cambiarla no es una transición. El estado Death del diagrama corresponde a GameState::GameOver, por eso
+ * no tiene un State: el evento Die sólo se cuenta.
+ *
+ * El estado inicial del jugador es Stand y las transiciones del diagrama son:
+ *   Stand  ---     UpKeyDown      ---> Jump
+ *   Stand  ---    SideKeyDown     ---> Walk
+ *   Stand  ---    DownKeyDown     ---> Crouch
+ *   Stand  ---      NoFloor       ---> Fall
+ *   Jump   ---  MaxHeightReached  ---> Fall
+ *   Jump   ---    DownKeyDown     ---> Meteor
+ *   Fall   ---    DownKeyDown     ---> Meteor
+ *   Fall   ---        Land        ---> Stand
+ *   Fall   ---     LandMoving     ---> Walk
+ *   Walk   ---     SideKeyUp      ---> Stand
+ *   Walk   ---     UpKeyDown      ---> Jump
+ *   Walk   ---      NoFloor       ---> Fall
+ *   Meteor ---        Land        ---> Crouch
+ *   Crouch ---     DownKeyUp      ---> Stand
+ * De cualquiera de estos estados se puede pasar a Death y concluir la ejecución de esa sesión de juego.
+ *
+ * Al leer el diagrama se revisa que tenga todos los estados del jugador y un solo estado inicial, que cada
+ * transición use un evento conocido y que no haya dos transiciones con el mismo estado y evento.
+ *
+ * La máquina se puede escribir como Graphviz (to_dot) o de nuevo como JFLAP (to_jff), opcionalmente con el
+ * número de veces que se usó cada transición (Character::transition_hits, en el orden de transitions). En DOT
+ * cada estado lleva su posición de JFLAP en pos, que respeta neato -n, y las transiciones que nunca se usaron
+ * se dibujan punteadas; en JFLAP las cuentas se escriben como comentarios para que el archivo se pueda volver
+ * a leer. El DOT del diagrama incluido se guarda en tests/golden/StateMachine_FoxHell.dot (en lugar de una
+ * imagen) y las pruebas lo comparan con to_dot; si el cambio es intencional se genera con FOX_HELL_BLESS=1.
+ */
+
+use std::fmt::Write;
+
+use crate::state::State;
+
+pub const JFF: &str = include_str!("../StateMachine_FoxHell.jff");
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Event {
+    UpKeyDown,
+    SideKeyDown,                                                            // Flecha izquierda o derecha
+    SideKeyUp,
+    DownKeyDown,
+    DownKeyUp,
+    MaxHeightReached,
+    NoFloor,
+    Land,
+    LandMoving,                                                             // Aterrizar con la flecha izquierda o derecha presionada
+    Die,
+}
+
+impl Event {
+    pub const ALL: [Event; 10] = [
+        Event::UpKeyDown,
+        Event::SideKeyDown,
+        Event::SideKeyUp,
+        Event::DownKeyDown,
+        Event::DownKeyUp,
+        Event::MaxHeightReached,
+        Event::NoFloor,
+        Event::Land,
+        Event::LandMoving,
+        Event::Die,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::UpKeyDown => "UpKeyDown",
+            Event::SideKeyDown => "SideKeyDown",
+            Event::SideKeyUp => "SideKeyUp",
+            Event::DownKeyDown => "DownKeyDown",
+            Event::DownKeyUp => "DownKeyUp",
+            Event::MaxHeightReached => "MaxHeightReached",
+            Event::NoFloor => "NoFloor",
+            Event::Land => "Land",
+            Event::LandMoving => "LandMoving",
+            Event::Die => "Die",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Event> {
+        Event::ALL.iter().copied().find(|event| event.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {                                                           // Estado del diagrama con su posición en JFLAP
+    pub name: String,
+    pub x: f32,
+    pub y: f32,
+    pub is_initial: bool,
+    pub is_final: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {                                                     // Los estados son índices de StateMachine::states
+    pub from: usize,
+    pub to: usize,
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateMachine {
+    pub states: Vec<Node>,
+    pub transitions: Vec<Transition>,
+}
+
+impl StateMachine {
+    pub fn embedded() -> StateMachine {                                     // La máquina del diagrama incluido en el binario
+        match StateMachine::from_jff(JFF) {
+            Ok(machine) => machine,
+            Err(err) => panic!("StateMachine_FoxHell.jff: {}", err),
+        }
+    }
+
+    pub fn from_jff(text: &str) -> Result<StateMachine, String> {
+        let automaton = match blocks(text, "automaton").first() {
+            Some((_, body)) => *body,
+            None => return Err(String::from("falta la sección automaton")),
+        };
+
+        let mut ids: Vec<String> = Vec::new();
+        let mut states: Vec<Node> = Vec::new();
+        for (head, body) in blocks(automaton, "state") {
+            let id = attribute(head, "id").ok_or_else(|| String::from("estado sin id"))?;
+            let name = element(body, "label")
+                .or_else(|| attribute(head, "name"))
+                .ok_or_else(|| format!("el estado {} no tiene nombre", id))?;
+            if states.iter().any(|node| node.name == name) {
+                return Err(format!("estado repetido '{}'", name));
+            }
+            states.push(Node {
+                name,
+                x: element(body, "x").and_then(|x| x.parse().ok()).unwrap_or(0.0),
+                y: element(body, "y").and_then(|y| y.parse().ok()).unwrap_or(0.0),
+                is_initial: body.contains("<initial/>"),
+                is_final: body.contains("<final/>"),
+            });
+            ids.push(id);
+        }
+
+        let mut transitions: Vec<Transition> = Vec::new();
+        for (_, body) in blocks(automaton, "transition") {
+            let state = |tag: &str| {
+                let id = element(body, tag).unwrap_or_default();
+                ids.iter().position(|i| *i == id).ok_or_else(|| format!("transición a un estado desconocido '{}'", id))
+            };
+            let (from, to) = (state("from")?, state("to")?);
+            let read = element(body, "read").unwrap_or_default();
+            let event = Event::from_name(&read).ok_or_else(|| format!("evento desconocido '{}'", read))?;
+            if transitions.iter().any(|t| t.from == from && t.event == event) {
+                return Err(format!("transición repetida {} --{}-->", states[from].name, event.name()));
+            }
+            transitions.push(Transition { from, to, event });
+        }
+
+        let machine = StateMachine { states, transitions };
+        match machine.states.iter().filter(|node| node.is_initial).count() {
+            0 => return Err(String::from("falta el estado inicial")),
+            1 => {},
+            _ => return Err(String::from("hay más de un estado inicial")),
+        }
+        if let Some(name) = State::NAMES.iter().find(|name| machine.index(name).is_none()) {
+            return Err(format!("falta el estado {}", name));
+        }
+        if !State::NAMES.contains(&machine.initial()) {
+            return Err(format!("el estado inicial '{}' no es un estado del jugador", machine.initial()));
+        }
+        Ok(machine)
+    }
+
+    pub fn index(&self, name: &str) -> Option<usize> {
+        self.states.iter().position(|node| node.name == name)
+    }
+
+    pub fn initial(&self) -> &str {
+        self.states.iter().find(|node| node.is_initial).map(|node| node.name.as_str()).unwrap_or("")
+    }
+
+    pub fn find(&self, from: &str, event: Event) -> Option<usize> {         // Índice de la transición del estado con el evento
+        let from = self.index(from)?;
+        self.transitions.iter().position(|t| t.from == from && t.event == event)
+    }
+
+    pub fn next(&self, from: &str, event: Event) -> Option<&str> {          // Estado al que lleva el evento, None si no hay transición
+        self.find(from, event).map(|i| self.states[self.transitions[i].to].name.as_str())
+    }
+
+    pub fn to_dot(&self, hits: Option<&[u32]>) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "digraph FoxHell {{");
+        let _ = writeln!(text, "    node [shape=circle];");
+        let _ = writeln!(text, "    start [shape=point];");
+        let _ = writeln!(text, "    start -> \"{}\";", quote(self.initial()));
+        for node in self.states.iter() {
+            let shape = if node.is_final { ", shape=doublecircle" } else { "" };
+            let _ = writeln!(text, "    \"{}\" [pos=\"{:.1},{:.1}!\"{}];", quote(&node.name), node.x, -node.y, shape);
+        }
+
+        let max = hits.and_then(|h| h.iter().copied().max()).unwrap_or(0).max(1);
+        for (i, t) in self.transitions.iter().enumerate() {
+            let (from, to) = (quote(&self.states[t.from].name), quote(&self.states[t.to].name));
+            let _ = match hits.map(|h| h.get(i).copied().unwrap_or(0)) {
+                None => writeln!(text, "    \"{}\" -> \"{}\" [label=\"{}\"];", from, to, t.event.name()),
+                Some(0) => writeln!(text, "    \"{}\" -> \"{}\" [label=\"{}\\n0\", style=dashed, color=gray];", from, to, t.event.name()),
+                Some(n) => writeln!(
+                    text,
+                    "    \"{}\" -> \"{}\" [label=\"{}\\n{}\", penwidth={:.1}];",
+                    from, to, t.event.name(), n, 1.0 + 4.0 * n as f32 / max as f32
+                ),
+            };
+        }
+        let _ = writeln!(text, "}}");
+        text
+    }
+
+    pub fn to_jff(&self, hits: Option<&[u32]>) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?><!--Created with fox_hell_fsm.--><structure>");
+        let _ = writeln!(text, "\t<type>fa</type>");
+        let _ = writeln!(text, "\t<automaton>");
+        let _ = writeln!(text, "\t\t<!--The list of states.-->");
+        for (i, node) in self.states.iter().enumerate() {
+            let _ = writeln!(text, "\t\t<state id=\"{}\" name=\"q{}\">", i, i);
+            let _ = writeln!(text, "\t\t\t<x>{:.1}</x>", node.x);
+            let _ = writeln!(text, "\t\t\t<y>{:.1}</y>", node.y);
+            let _ = writeln!(text, "\t\t\t<label>{}</label>", escape(&node.name));
+            if node.is_initial {
+                let _ = writeln!(text, "\t\t\t<initial/>");
+            }
+            if node.is_final {
+                let _ = writeln!(text, "\t\t\t<final/>");
+            }
+            let _ = writeln!(text, "\t\t</state>");
+        }
+        let _ = writeln!(text, "\t\t<!--The list of transitions.-->");
+        for (i, t) in self.transitions.iter().enumerate() {
+            let _ = writeln!(text, "\t\t<transition>");
+            let _ = writeln!(text, "\t\t\t<from>{}</from>", t.from);
+            let _ = writeln!(text, "\t\t\t<to>{}</to>", t.to);
+            let _ = writeln!(text, "\t\t\t<read>{}</read>", t.event.name());
+            if let Some(hits) = hits {
+                let _ = writeln!(text, "\t\t\t<!--Hits: {}-->", hits.get(i).copied().unwrap_or(0));
+            }
+            let _ = writeln!(text, "\t\t</transition>");
+        }
+        let _ = writeln!(text, "\t</automaton>");
+        let _ = write!(text, "</structure>");
+        text
+    }
+}
+
+fn blocks<'a>(text: &'a str, tag: &str) -> Vec<(&'a str, &'a str)> {       // Atributos y contenido de cada elemento <tag ...>...</tag>
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut list = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        if !after.starts_with(|c: char| c == '>' || c.is_whitespace()) {   // Otro elemento que empieza igual
+            rest = after;
+            continue;
+        }
+        let (head, body) = match after.find('>') {
+            Some(end) => (&after[..end], &after[end + 1..]),
+            None => break,
+        };
+        match body.find(&close) {
+            Some(end) => {
+                list.push((head, &body[..end]));
+                rest = &body[end + close.len()..];
+            },
+            None => break,
+        }
+    }
+    list
+}
+
+fn element(text: &str, tag: &str) -> Option<String> {
+    blocks(text, tag).first().map(|(_, body)| unescape(body))
+}
+
+fn attribute(head: &str, name: &str) -> Option<String> {
+    let start = head.find(&format!("{}=\"", name))? + name.len() + 2;
+    let end = head[start..].find('"')?;
+    Some(unescape(&head[start..start + end]))
+}
+
+fn quote(text: &str) -> String {                                            // Texto dentro de comillas en DOT
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&#13;", "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn rejects_events_missing_from_the_diagram() {
+        let machine = StateMachine::embedded();
+        assert_eq!(machine.next("Stand", Event::UpKeyDown), Some("Jump"));
+        assert_eq!(machine.next("Stand", Event::Land), None);             // Stand no tiene transición con Land
+        assert_eq!(machine.next("Meteor", Event::UpKeyDown), None);
+        assert_eq!(machine.find("Crouch", Event::SideKeyDown), None);
+
+        let unknown = JFF.replacen("<read>UpKeyDown</read>", "<read>Fly</read>", 1);
+        assert_eq!(StateMachine::from_jff(&unknown), Err(String::from("evento desconocido 'Fly'")));
+    }
+
+    #[test]
+    fn rejects_a_bad_initial_state() {
+        let without = JFF.replacen("<initial/>", "", 1);
+        assert_eq!(StateMachine::from_jff(&without), Err(String::from("falta el estado inicial")));
+        let death = without.replacen("<label>Death</label>", "<label>Death</label><initial/>", 1);
+        assert_eq!(StateMachine::from_jff(&death), Err(String::from("el estado inicial 'Death' no es un estado del jugador")));
+        let two = JFF.replacen("<label>Walk</label>", "<label>Walk</label><initial/>", 1);
+        assert_eq!(StateMachine::from_jff(&two), Err(String::from("hay más de un estado inicial")));
+    }
+
+    #[test]
+    fn rejects_duplicate_transitions() {
+        let start = JFF.find("<transition>").unwrap();
+        let end = start + JFF[start..].find("</transition>").unwrap() + "</transition>".len();
+        let duplicate = format!("{}{}{}", &JFF[..end], &JFF[start..end], &JFF[end..]);
+        assert!(StateMachine::from_jff(&duplicate).unwrap_err().starts_with("transición repetida"));
+    }
+
+    #[test]
+    fn jff_output_reads_back() {
+        let machine = StateMachine::embedded();
+        assert_eq!(StateMachine::from_jff(&machine.to_jff(None)), Ok(machine.clone()));
+        let hits: Vec<u32> = (0..machine.transitions.len() as u32).collect();
+        let text = machine.to_jff(Some(&hits));
+        assert!(text.contains("<!--Hits: 3-->"));
+        assert_eq!(StateMachine::from_jff(&text), Ok(machine));
+    }
+
+    #[test]
+    fn dot_labels_show_the_hits() {
+        let machine = StateMachine::embedded();
+        let mut hits = vec![0; machine.transitions.len()];
+        let jump = machine.find("Stand", Event::UpKeyDown).unwrap();
+        hits[jump] = 12;
+        let text = machine.to_dot(Some(&hits));
+        assert!(text.contains("\"Stand\" -> \"Jump\" [label=\"UpKeyDown\\n12\", penwidth=5.0];"));
+        assert!(text.contains("\"Stand\" -> \"Walk\" [label=\"SideKeyDown\\n0\", style=dashed, color=gray];"));
+        assert!(!machine.to_dot(None).contains("\\n"));
+    }
+
+    #[test]
+    fn dot_matches_the_golden_diagram() {                               // Reemplaza a StateMachine_FoxHell.png, se guarda con FOX_HELL_BLESS=1
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/StateMachine_FoxHell.dot");
+        let text = StateMachine::embedded().to_dot(None);
+        if env::var_os("FOX_HELL_BLESS").is_some() {
+            fs::write(&path, &text).unwrap();
+            return;
+        }
+        let golden = fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+        assert_eq!(text, golden, "el diagrama cambió, revisa {} con dot -Tpng", path.display());
+    }
+}
@@ -1,70 +1,222 @@
 /*
  * Clase que contiene un mapa de juego usando una lista de sprites y un mapa de bits indicando qué sprite debemos
  * poner en cada uno de los espacios del mapa creado. Este se imprime al fondo del canvas.
+ *
+ * El ancho y el alto del mapa se toman del archivo: cada línea entre MapStart y MapEnd es una fila, y todas
+ * deben tener el ancho de la primera. Con centered se obtiene
+ * una copia del mapa centrada en otro tamaño, para que las pantallas de inicio, fin y pausa se ajusten al
+ * tamaño del nivel.
+ *
+ * La sección opcional OptionsStart/OptionsEnd contiene propiedades del mapa con el formato Nombre:Valor:
+ *   Wrap:true       al salir por un lado del mapa se aparece por el otro (por defecto no da la vuelta)
+ *   Viewport:20     ancho de la ventana visible, si el mapa es más ancho la pantalla se desplaza siguiendo
+ *                   al zorro (por defecto se ve el mapa completo)
+ *
+ * En la sección de sprites se pueden declarar paletas compartidas (@Nombre:colores, ver sprite.rs), que no
+ * cuentan como celdas: el sprite 1 es la primera línea que no es una paleta.
+ *
+ * Cada sprite tiene además sus propiedades (TileProperties, ver tile.rs) en la lista tiles: si es sólido, si
+ * hace daño, si se puede recoger y cómo se anima. El jugador consulta estas propiedades con properties y
+ * animate en lugar de depender del número de cada sprite, y el HUD busca las vidas y los dígitos con hud_tile.
+ *
+ * from_text regresa un error con la línea si una fila no tiene el ancho de la primera, y en lo demás supone
+ * que el archivo es correcto (un sprite con errores queda transparente); los mapas hechos a mano se pueden
+ * revisar antes con validate::check_map, que indica la línea y la columna de cada error.
  */
 
-use crate::sprite::Sprite;
+use crate::bitmap::BitMap;
+use crate::sprite::{Palettes, Sprite};
+use crate::tile::{AnimationKind, HudRole, TileProperties};
 
-#[derive(Debug)]
+pub const MAX_SPRITES: usize = 35;                                          // Sprites que pueden usar las celdas (1-9 y A-Z)
+
+#[derive(Debug, Clone)]
 pub struct GameMap {
-    pub map: [[u8; 20]; 10],
+    pub map: Vec<Vec<u8>>,
     pub sprites: Vec<Sprite>,
-    i: u8,
+    pub tiles: Vec<TileProperties>,
     pub bg: String,
     pub bg_temp: String,
     pub bg_alter: bool,
+    pub wrap: bool,
+    pub viewport: Option<u32>,
+}
+
+impl Default for GameMap {
+    fn default() -> Self {
+        GameMap::new()
+    }
 }
 
 impl GameMap {
     pub fn new() -> GameMap {
         GameMap {
-            map: [[0; 20]; 10],
+            map: Vec::new(),
             sprites: Vec::new(),
-            i: 0,
+            tiles: Vec::new(),
             bg: String::new(),
             bg_temp: String::new(),
             bg_alter: false,
+            wrap: false,
+            viewport: None,
         }
     }
 
-    pub fn from_text(text: String) -> GameMap {
+    pub fn from_text(text: String) -> Result<GameMap, (usize, String)> {    // El error indica la línea y la razón
         let mut map = GameMap::new();
+        let mut palettes = Palettes::new();
 
         let mut state=0;
-        for line in text.lines() {
+        for (i, line) in text.lines().enumerate() {
             match line {
                 "BackgroundStart"                                               => { state=3 },
                 "BackTempStart"                                                 => { state=4 },
                 "MapStart"                                                      => { state=1 },
                 "SpriteStart"                                                   => { state=2 },
-                "" | "MapEnd" | "SpriteEnd" | "BackgroundEnd" | "BackTempEnd"   => { state=0 },
+                "OptionsStart"                                                  => { state=5 },
+                "" | "MapEnd" | "SpriteEnd" | "BackgroundEnd" | "BackTempEnd" | "OptionsEnd" => { state=0 },
                 _ => {
                     match state {
-                        1 => { map.add_line(line) },
-                        2 => { map.add_sprite(Sprite::from_line(line.to_owned())) },
+                        1 => {
+                            map.add_line(line);
+                            let (len, width) = (map.map[map.map.len() - 1].len(), map.map[0].len());
+                            if len != width {
+                                return Err((i + 1, format!("fila de {} celdas, se esperaban {}", len, width)));
+                            }
+                        },
+                        2 if line.starts_with('@') => {                 // Las paletas no son celdas del mapa
+                            if let Some((name, colors)) = Sprite::parse_palette(line) {
+                                palettes.insert(name, colors);
+                            }
+                        },
+                        2 => {
+                            let spte = Sprite::from_line_with(line.to_owned(), &palettes)  // Un sprite con errores queda transparente para no mover
+                                .unwrap_or_else(|_| Sprite::with_map(Vec::new(), BitMap::new())); // las celdas de los demás
+                            map.add_sprite(spte);
+                            map.tiles[map.sprites.len() - 1] = TileProperties::from_line(line);
+                        },
                         3 => { map.bg.push_str(line) },
                         4 => { map.bg_temp.push_str(line) },
+                        5 => { map.add_option(line) },
                         _ => (),
                     }
                 }
             }
         }
-        map
+        Ok(map)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.map.iter().map(|row| row.len()).max().unwrap_or(0) as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.map.len() as u32
     }
 
     pub fn add_line(&mut self, line: &str) {
-        for (i, c) in line.chars().enumerate() {
-            if c >= '0' && c <= '9' {
-                self.map[self.i as usize][i] = (c as u8 - '0' as u8) as u8;
-            } else if c >= 'A' && c <= 'Z' {
-                self.map[self.i as usize][i] = ((c as u8) - ('A' as u8) + (10 as u8) ) as u8;
+        let mut row = Vec::new();
+        for c in line.chars() {
+            if c.is_ascii_digit() {
+                row.push(c as u8 - b'0');
+            } else if c.is_ascii_uppercase() {
+                row.push(c as u8 - b'A' + 10);
+            } else {
+                row.push(0);
             }
         }
-        self.i+=1;
+        self.map.push(row);
+    }
+
+    pub fn centered_offset(&self, width: u32, height: u32) -> (i32, i32) {    // Desplazamiento de las celdas al centrar el mapa en width x height
+        (
+            (width as i32 - self.width() as i32) / 2,
+            (height as i32 - self.height() as i32) / 2,
+        )
+    }
+
+    pub fn centered(&self, width: u32, height: u32) -> GameMap {                // Copia del mapa centrada en width x height, recortando lo que no cabe
+        let (dx, dy) = self.centered_offset(width, height);
+        let mut map = vec![vec![0; width as usize]; height as usize];
+        for (i, row) in self.map.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                let x = j as i32 + dx;
+                let y = i as i32 + dy;
+                if x >= 0 && y >= 0 && x < width as i32 && y < height as i32 {
+                    map[y as usize][x as usize] = *tile;
+                }
+            }
+        }
+        GameMap {
+            map,
+            sprites: self.sprites.iter().map(|spte| spte.deref()).collect(),
+            tiles: self.tiles.clone(),
+            bg: self.bg.clone(),
+            bg_temp: self.bg_temp.clone(),
+            bg_alter: self.bg_alter,
+            wrap: false,
+            viewport: None,
+        }
+    }
+
+    pub fn add_option(&mut self, line: &str) {
+        let mut parts = line.splitn(2, ':');
+        match (parts.next(), parts.next().map(|v| v.trim())) {
+            (Some("Wrap"), Some(value)) => self.wrap = value == "true",
+            (Some("Viewport"), Some(value)) => self.viewport = value.parse().ok(),
+            _ => (),
+        }
+    }
+
+    pub fn view_width(&self) -> u32 {                                           // Ancho de la ventana visible del mapa
+        self.viewport.unwrap_or_else(|| self.width()).min(self.width())
     }
 
     pub fn add_sprite(&mut self, spte: Sprite) {
         self.sprites.push(spte);
+        self.tiles.push(TileProperties::default());
+    }
+
+    pub fn properties(&self, x: u32, y: u32) -> TileProperties {                // Propiedades de la celda (x, y), una celda vacía o fuera del mapa no tiene propiedades
+        match self.map.get(y as usize).and_then(|row| row.get(x as usize)) {
+            Some(tile) if *tile != 0 => self.tiles.get(*tile as usize - 1).copied().unwrap_or_default(),
+            _ => TileProperties::default(),
+        }
+    }
+
+    pub fn hud_tile(&self, role: HudRole) -> Option<u8> {                     // Celda del sprite que declara la propiedad hud indicada
+        self.tiles.iter().position(|props| props.hud == Some(role)).map(|i| i as u8 + 1)
+    }
+
+    pub fn digit_tiles(&self, text: &str) -> Vec<u8> {                          // Celdas con los dígitos de text, 0 (vacía) si el mapa no tiene el dígito
+        text.chars()
+            .map(|c| c.to_digit(10).and_then(|d| self.hud_tile(HudRole::Digit(d as u8))).unwrap_or(0))
+            .collect()
+    }
+
+    pub fn animate(&mut self, it: usize) {                                      // Anima los sprites cuyo periodo coincide con la iteración it
+        for (spte, props) in self.sprites.iter_mut().zip(self.tiles.iter()) {
+            match props.animation {
+                Some(anim) if it.is_multiple_of(anim.period) => match anim.kind {
+                    AnimationKind::Invert => spte.map.invert_side(),
+                    AnimationKind::Rotate => spte.map.right_rotate(),
+                },
+                _ => (),
+            }
+        }
     }
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_rows_of_different_width() {
+        let text = "MapStart\n000\n0100\n000\nMapEnd\nSpriteStart\nWall:red:0-0-0\nSpriteEnd\n";
+        assert_eq!(GameMap::from_text(text.to_string()).err(), Some((3, String::from("fila de 4 celdas, se esperaban 3"))));
+        let map = GameMap::from_text(text.replace("0100", "010")).unwrap();
+        assert_eq!((map.width(), map.height()), (3, 3));
+    }
+}
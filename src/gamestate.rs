@@ -2,9 +2,12 @@
  * Enumeración que indica los posibles estados en que puede estar la ejecución del juego.
  */
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum GameState {
     Start,
     Play,
+    Paused,
     GameOver,
+    LevelComplete,
+    Victory,
 }
@@ -0,0 +1,135 @@
+/*
+ * Panel del status con el diagrama de la máquina de estados. StateGraph no sabe nada de los estados del
+ * zorro: recibe una lista de estados y de transiciones con su etiqueta (from_machine las toma de fsm.rs) y
+ * los acomoda solo, así que agregar un estado al diagrama no requiere mover coordenadas a mano.
+ *
+ * Los estados se reparten en un círculo en la parte superior del panel, empezando arriba (el primer estado)
+ * y en el sentido de las manecillas del reloj. Todas las posiciones están normalizadas entre 0 y 1 respecto
+ * al ancho y al alto del panel, cada Renderer las escala a su tamaño. Cada transición es una flecha recta
+ * entre los bordes de los dos estados, con su etiqueta a la mitad; si hay transiciones en los dos sentidos
+ * entre dos estados cada una se separa un poco hacia su derecha para que no se encimen, y una transición de
+ * un estado a sí mismo es un pequeño lazo arriba del estado.
+ *
+ * Además de la forma del diagrama se indica el estado activo, la última transición que se usó y un
+ * historial con las transiciones más recientes (como las escribe Character::get_transition), que se
+ * muestra debajo del diagrama con la más reciente al final.
+ */
+
+use std::f64::consts::PI;
+
+use crate::fsm::StateMachine;
+
+pub const HISTORY_LEN: usize = 5;                                           // Transiciones que se muestran en el historial
+pub const NODE_RADIUS: f64 = 0.055;
+pub const HISTORY_TOP: f64 = 0.78;                                          // Altura de la primera línea del historial
+pub const HISTORY_STEP: f64 = 0.045;
+
+const CENTER: (f64, f64) = (0.5, 0.37);
+const LAYOUT_RADIUS: f64 = 0.29;
+const PARALLEL_OFFSET: f64 = 0.015;
+const ARROW_SIZE: f64 = 0.025;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub label: String,
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StateGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    pub active: Option<usize>,                                              // Índice del estado actual
+    pub last_edge: Option<usize>,                                           // Índice de la última transición
+    pub history: Vec<String>,
+}
+
+impl StateGraph {
+    pub fn new(labels: &[String], edges: Vec<GraphEdge>) -> StateGraph {    // Acomoda los estados en un círculo
+        let count = labels.len().max(1) as f64;
+        let nodes = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                let angle = -PI / 2.0 + 2.0 * PI * i as f64 / count;
+                GraphNode {
+                    label: label.clone(),
+                    x: CENTER.0 + LAYOUT_RADIUS * angle.cos(),
+                    y: CENTER.1 + LAYOUT_RADIUS * angle.sin(),
+                }
+            })
+            .collect();
+        StateGraph {
+            nodes,
+            edges,
+            ..StateGraph::default()
+        }
+    }
+
+    pub fn from_machine(machine: &StateMachine) -> StateGraph {             // Las transiciones quedan en el mismo orden que en la máquina
+        let labels: Vec<String> = machine.states.iter().map(|node| node.name.clone()).collect();
+        let edges = machine
+            .transitions
+            .iter()
+            .map(|t| GraphEdge { from: t.from, to: t.to, label: t.event.name().to_string() })
+            .collect();
+        StateGraph::new(&labels, edges)
+    }
+
+    pub fn edge_path(&self, edge: usize) -> Vec<(f64, f64)> {               // Puntos de la línea de una transición, la flecha va en el último
+        let e = &self.edges[edge];
+        let from = &self.nodes[e.from];
+        if e.from == e.to {
+            let (x, y, r) = (from.x, from.y, NODE_RADIUS);
+            return vec![(x - r * 0.5, y - r * 0.85), (x - r * 0.5, y - r * 1.8), (x + r * 0.5, y - r * 1.8), (x + r * 0.5, y - r * 0.85)];
+        }
+        let to = &self.nodes[e.to];
+        let ((dx, dy), (nx, ny)) = direction(from, to);
+        let offset = if self.edges.iter().any(|o| o.from == e.to && o.to == e.from) { PARALLEL_OFFSET } else { 0.0 };
+        vec![
+            (from.x + dx * NODE_RADIUS + nx * offset, from.y + dy * NODE_RADIUS + ny * offset),
+            (to.x - dx * NODE_RADIUS + nx * offset, to.y - dy * NODE_RADIUS + ny * offset),
+        ]
+    }
+
+    pub fn arrow_head(&self, edge: usize) -> [(f64, f64); 3] {              // Triángulo de la flecha: la punta y las dos alas
+        let path = self.edge_path(edge);
+        let (x0, y0) = path[path.len() - 2];
+        let (x1, y1) = path[path.len() - 1];
+        let length = ((x1 - x0).powi(2) + (y1 - y0).powi(2)).sqrt().max(f64::EPSILON);
+        let (dx, dy) = ((x1 - x0) / length, (y1 - y0) / length);
+        let (bx, by) = (x1 - dx * ARROW_SIZE, y1 - dy * ARROW_SIZE);
+        let (nx, ny) = (-dy * ARROW_SIZE / 2.0, dx * ARROW_SIZE / 2.0);
+        [(x1, y1), (bx + nx, by + ny), (bx - nx, by - ny)]
+    }
+
+    pub fn label_pos(&self, edge: usize) -> (f64, f64) {                    // Centro de la etiqueta de una transición
+        let path = self.edge_path(edge);
+        let e = &self.edges[edge];
+        if e.from == e.to {
+            return (self.nodes[e.from].x, path[1].1 - 0.015);
+        }
+        let (nx, ny) = direction(&self.nodes[e.from], &self.nodes[e.to]).1;
+        let (x0, y0) = path[0];
+        let (x1, y1) = path[1];
+        ((x0 + x1) / 2.0 + nx * 0.02, (y0 + y1) / 2.0 + ny * 0.02)
+    }
+
+    pub fn history_pos(&self, line: usize) -> (f64, f64) {                  // Inicio de una línea del historial
+        (0.05, HISTORY_TOP + HISTORY_STEP * line as f64)
+    }
+}
+
+fn direction(from: &GraphNode, to: &GraphNode) -> ((f64, f64), (f64, f64)) {  // Dirección unitaria de from a to y su perpendicular hacia la derecha
+    let length = ((to.x - from.x).powi(2) + (to.y - from.y).powi(2)).sqrt().max(f64::EPSILON);
+    let (dx, dy) = ((to.x - from.x) / length, (to.y - from.y) / length);
+    ((dx, dy), (-dy, dx))
+}
@@ -0,0 +1,55 @@
+/*
+ * Enumeración con los eventos del teclado que entiende el jugador. Cada interfaz traduce sus propias teclas
+ * (eventos de JavaScript, bytes de una terminal...) a estos eventos y los aplica con Character::apply_input,
+ * de esta forma también se pueden grabar y reproducir.
+ *
+ * Los nombres que regresa name() son los que se usan en los archivos de repeticiones.
+ */
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum Input {
+    LeftDown,
+    LeftUp,
+    RightDown,
+    RightUp,
+    UpDown,
+    UpUp,
+    DownDown,
+    DownUp,
+    Start,
+    Pause,
+}
+
+impl Input {
+    pub const ALL: [Input; 10] = [
+        Input::LeftDown,
+        Input::LeftUp,
+        Input::RightDown,
+        Input::RightUp,
+        Input::UpDown,
+        Input::UpUp,
+        Input::DownDown,
+        Input::DownUp,
+        Input::Start,
+        Input::Pause,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Input::LeftDown => "LeftDown",
+            Input::LeftUp => "LeftUp",
+            Input::RightDown => "RightDown",
+            Input::RightUp => "RightUp",
+            Input::UpDown => "UpDown",
+            Input::UpUp => "UpUp",
+            Input::DownDown => "DownDown",
+            Input::DownUp => "DownUp",
+            Input::Start => "Start",
+            Input::Pause => "Pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Input> {
+        Input::ALL.iter().copied().find(|input| input.name() == name)
+    }
+}
@@ -0,0 +1,38 @@
+/*
+ * Núcleo del juego independiente de la plataforma. Aquí se encuentran todas las reglas del juego: la máquina
+ * de estados del jugador, el movimiento, el seguimiento de los proyectiles, el puntaje y las vidas, así como
+ * la carga de mapas y sprites.
+ *
+ * Ninguno de estos módulos depende de stdweb, por lo que el núcleo compila tanto para wasm32 como para
+ * cualquier plataforma nativa y puede ejecutarse desde cargo test o desde otras herramientas. Las interfaces
+ * (por ejemplo el canvas web en main.rs) sólo implementan el trait Renderer para dibujar el juego y
+ * traducen las teclas a llamadas sobre Character.
+ */
+
+pub mod arena;                                                              // Generador de arenas para el modo sin fin
+pub mod assets;                                                             // Archivos del juego incluidos o cargados en ejecución
+pub mod bitmap;                                                             // Mapa de bits de un sprite y sus operaciones
+pub mod camera;                                                             // Ventana visible de los niveles más anchos que la pantalla
+pub mod campaign;                                                           // Lista de niveles de la partida
+pub mod character;                                                          // Lógica que rodea al jugador
+pub mod color;                                                              // Conversión de colores de CSS a RGBA
+pub mod direction;                                                          // Enumeración de posibles direcciones
+pub mod fsm;                                                                // Máquina de estados del jugador leída de StateMachine_FoxHell.jff
+pub mod framebuffer;                                                        // Renderer sobre un buffer RGBA con exportación a PNG/PPM
+pub mod gamemap;                                                            // Mapa del juego y sus operaciones
+pub mod gamestate;                                                          // Enumeración de posibles estados del juego
+pub mod graph;                                                              // Diagrama de la máquina de estados para el panel de status
+pub mod input;                                                              // Eventos del teclado que entiende el jugador
+pub mod physics;                                                            // Física del salto y de la caída en unidades de sub-celda
+pub mod projectile;                                                         // Sprites de tipo proyectil
+pub mod random;                                                             // Fuente de números aleatorios con semilla
+pub mod renderer;                                                           // Interfaz para dibujar el juego en cualquier medio
+pub mod replay;                                                             // Grabación y reproducción de partidas
+pub mod snapshot;                                                           // Fotografía completa del estado del juego
+pub mod sprite;                                                             // Sprite con un mapa de bits de cualquier tamaño y la lista de colores
+pub mod spritesheet;                                                        // Conversión entre sprites e imágenes PNG
+pub mod state;                                                              // Enumeración de posibles estados del jugador
+pub mod terminal;                                                           // Renderer para terminales con colores de 24 bits
+pub mod theme;                                                              // Temas que cambian los colores de sprites y fondos
+pub mod tile;                                                               // Propiedades de las celdas de los mapas
+pub mod validate;                                                           // Revisión de los archivos de mapas, sprites y niveles
@@ -0,0 +1,163 @@
+/*
+ * Física del salto y de la caída del zorro. La altura se mide en unidades de sub-celda (UNITS por celda) y
+ * cada update la velocidad vertical cambia con la gravedad:
+ *   - Al saltar la velocidad empieza en JUMP_SPEED hacia arriba. Mientras se mantiene la flecha ARRIBA la
+ *     gravedad es HELD_GRAVITY, así que un salto completo sube algo más de MAX_JUMP_H celdas; al soltarla
+ *     la velocidad de subida se recorta a CUT_SPEED y se usa la gravedad normal, por lo que un toque corto
+ *     da un salto bajo. Cuando la velocidad llega a 0 se alcanza la altura máxima (Character dispara
+ *     MaxHeightReached, de Jump a Fall) y el zorro empieza a caer.
+ *   - Al caer la velocidad aumenta GRAVITY por update hasta TERMINAL_SPEED (media celda por update, la misma
+ *     velocidad con la que caía antes el zorro).
+ *   - El Meteor cae siempre a METEOR_SPEED, una celda por update.
+ * El movimiento horizontal es de WALK_SPEED por update (una columna cada dos updates) y los proyectiles
+ * avanzan PROJECTILE_SPEED por update (una celda cada cuatro).
+ *
+ * Al subir se atraviesan las plataformas; al bajar el zorro se detiene en cuanto su parte inferior llega a
+ * una celda sólida. Character guarda la posición del zorro en unidades (pos_x, pos_y) y las reglas del mapa
+ * (suelo, celdas peligrosas, objetos) usan la celda más cercana (cell). Cada update se calcula con walk, rise,
+ * fall_speed y descend, que también usa el generador de arenas para saber a dónde llega un salto.
+ *
+ * Las colisiones entre el zorro y los proyectiles se revisan con cajas de una celda que se traslapan
+ * (overlap). A la caja de las rocas de lava se le recorta HIT_MARGIN de cada lado para que un roce no quite
+ * una vida; las manzanas usan la caja completa. Como el juego se actualiza cada 45 ms, las interfaces dibujan
+ * el zorro y los proyectiles entre su posición anterior y la actual (interpolate) según el tiempo que pasó
+ * desde el último update.
+ */
+
+pub const UNITS: i32 = 32;                                                  // Unidades por celda
+pub const JUMP_SPEED: i32 = 22;                                             // Velocidad inicial del salto, unidades por update
+pub const HELD_GRAVITY: i32 = 2;                                            // Gravedad al subir con la flecha ARRIBA presionada
+pub const GRAVITY: i32 = 4;
+pub const CUT_SPEED: i32 = 12;                                              // Velocidad máxima de subida después de soltar ARRIBA
+pub const TERMINAL_SPEED: i32 = 16;
+pub const METEOR_SPEED: i32 = 32;
+pub const WALK_SPEED: i32 = UNITS / 2;
+pub const PROJECTILE_SPEED: i32 = UNITS / 4;
+pub const HIT_MARGIN: i32 = UNITS / 4;                                      // Unidades que se recortan de cada lado de la caja de una roca de lava
+
+pub const fn jump_height() -> i32 {                                         // Unidades que sube un salto manteniendo ARRIBA
+    let mut speed = JUMP_SPEED;
+    let mut height = 0;
+    while speed > 0 {
+        height += speed;
+        speed -= HELD_GRAVITY;
+    }
+    height
+}
+
+pub const MAX_JUMP_H: u8 = (jump_height() / UNITS) as u8;                   // Celdas completas que sube un salto completo
+
+pub fn walk(pos_x: i32, dx: i32, width: u32, wrap: bool) -> i32 {          // Posición en unidades después de un update caminando en la dirección dx (-1, 0 o 1)
+    let x = pos_x + dx * WALK_SPEED;
+    let world = width as i32 * UNITS;
+    if wrap {
+        x.rem_euclid(world)
+    } else {
+        x.clamp(0, world - UNITS)
+    }
+}
+
+pub fn rise(pos_y: i32, vel_y: i32, held: bool) -> (i32, i32) {            // Un update de subida, regresa la altura y la velocidad (0 al llegar a la altura máxima)
+    let pos_y = (pos_y + vel_y).max(0);
+    let vel_y = vel_y + if held { HELD_GRAVITY } else { GRAVITY };
+    if vel_y >= 0 || pos_y == 0 {
+        (pos_y, 0)
+    } else {
+        (pos_y, vel_y)
+    }
+}
+
+pub fn fall_speed(vel_y: i32) -> i32 {                                      // Velocidad de caída después de un update
+    (vel_y + GRAVITY).min(TERMINAL_SPEED)
+}
+                                                                            // Baja hasta amount unidades revisando el suelo en cada celda que se atraviesa;
+pub fn descend<F: Fn(u32) -> bool>(pos_y: i32, amount: i32, solid_below: F) -> (i32, bool) { // solid_below(fila) indica si hay suelo debajo de la fila
+    let landed = |y: i32| y % UNITS == 0 && solid_below(cell(y));
+    let mut pos_y = pos_y;
+    let mut left = amount;
+    while left > 0 && !landed(pos_y) {
+        let step = (UNITS - pos_y % UNITS).min(left);
+        pos_y += step;
+        left -= step;
+    }
+    (pos_y, landed(pos_y))
+}
+
+pub fn cell(pos: i32) -> u32 {                                              // Celda más cercana a una posición en unidades
+    (pos.saturating_add(UNITS / 2) / UNITS) as u32
+}
+
+pub fn overlap(a: (i32, i32), b: (i32, i32), margin: i32) -> bool {         // Indica si dos cajas de una celda, recortadas margin de cada lado, se traslapan
+    let size = UNITS - 2 * margin;
+    (a.0 - b.0).abs() < size && (a.1 - b.1).abs() < size
+}
+
+pub fn interpolate(last: (i32, i32), now: (i32, i32), alpha: f64) -> (f64, f64) { // Posición en celdas entre la anterior y la actual, alpha entre 0 y 1
+    if (now.0 - last.0).abs() > UNITS || (now.1 - last.1).abs() > UNITS {
+        return (now.0 as f64 / UNITS as f64, now.1 as f64 / UNITS as f64); // Un salto más grande es una vuelta del mapa o un cambio de nivel
+    }
+    let alpha = alpha.clamp(0.0, 1.0);
+    let x = last.0 as f64 + (now.0 - last.0) as f64 * alpha;
+    let y = last.1 as f64 + (now.1 - last.1) as f64 * alpha;
+    (x / UNITS as f64, y / UNITS as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn climb(held_updates: usize) -> i32 {                                  // Unidades que sube un salto con ARRIBA presionada los primeros updates
+        let start = 10 * UNITS;
+        let (mut pos_y, mut vel_y) = (start, -JUMP_SPEED);
+        let mut updates = 0;
+        while vel_y < 0 {
+            (pos_y, vel_y) = rise(pos_y, vel_y, updates < held_updates);
+            updates += 1;
+        }
+        start - pos_y
+    }
+
+    #[test]
+    fn holding_jump_climbs_higher_than_a_tap() {
+        let held = climb(usize::MAX);
+        assert_eq!(held, jump_height());
+        assert_eq!(held / UNITS, MAX_JUMP_H as i32);
+        let tap = climb(1);
+        assert!(tap < held);
+        assert!(tap / UNITS < MAX_JUMP_H as i32);
+        assert!(climb(4) > tap && climb(4) < held);
+    }
+
+    #[test]
+    fn falling_stops_speeding_up_at_terminal_speed() {
+        let mut vel_y = 0;
+        for _ in 0..20 {
+            let next = fall_speed(vel_y);
+            assert!(next >= vel_y && next <= TERMINAL_SPEED);
+            vel_y = next;
+        }
+        assert_eq!(vel_y, TERMINAL_SPEED);
+        assert_eq!(fall_speed(-CUT_SPEED), -CUT_SPEED + GRAVITY);
+    }
+
+    #[test]
+    fn lava_hits_only_inside_the_margin() {
+        let reach = UNITS - 2 * HIT_MARGIN;                                 // Distancia desde la que ya no se traslapan las cajas recortadas
+        assert!(overlap((64, 64), (64 + reach - 1, 64), HIT_MARGIN));
+        assert!(overlap((64, 64), (64, 64 - reach + 1), HIT_MARGIN));
+        assert!(!overlap((64, 64), (64 + reach, 64), HIT_MARGIN));
+        assert!(!overlap((64, 64), (64 - reach, 64 + reach - 1), HIT_MARGIN));
+        assert!(overlap((64, 64), (64 + reach, 64), 0));                    // Una manzana a la misma distancia sí se recoge
+        assert!(!overlap((64, 64), (64 + UNITS, 64), 0));
+    }
+
+    #[test]
+    fn interpolate_starts_at_the_last_position_and_ends_at_the_new_one() {
+        let (last, now) = ((2 * UNITS, 3 * UNITS), (2 * UNITS + WALK_SPEED, 3 * UNITS - 10));
+        assert_eq!(interpolate(last, now, 0.0), (2.0, 3.0));
+        assert_eq!(interpolate(last, now, 1.0), (2.5, (3 * UNITS - 10) as f64 / UNITS as f64));
+        assert_eq!(interpolate(last, now, 0.5), (2.25, (3 * UNITS - 5) as f64 / UNITS as f64));
+        assert_eq!(interpolate(last, now, 2.0), interpolate(last, now, 1.0));
+        assert_eq!(interpolate((0, 0), (19 * UNITS, 0), 0.0), (19.0, 0.0));  // Vuelta del mapa
+    }
+}
@@ -9,17 +9,26 @@
  * pacíficos y vertical hacia abajo para los hostiles.
  * 
  * Se dispara un proyectil con el método shoot_at y ahí se define si es hostil (kill = true) o no (kill = false).
- * En ese método se genera el proyectil con el sprite indicado.
+ * En ese método se genera el proyectil con el sprite indicado. La fuente de números aleatorios (RandomSource) se
+ * recibe como parámetro para no depender de la plataforma y para que los disparos se puedan reproducir.
+ * 
+ * Los límites del mapa (x_max, y_max) se reciben al disparar, así que los proyectiles funcionan con mapas de
+ * cualquier tamaño. En los niveles más anchos que la pantalla se dispara con las coordenadas de la ventana
+ * visible y después to_world pasa el proyectil a las coordenadas del nivel.
  * 
  * El otro método se encarga de mantener la trayectoria de un pryectil dependiendo de su posición (coordenadas y
- * dirección).
+ * dirección). Las coordenadas están en unidades de sub-celda (ver physics.rs) y en cada llamada el proyectil
+ * avanza PROJECTILE_SPEED; los límites del mapa siguen en celdas. last_coord regresa la posición anterior
+ * al último movimiento, para dibujar el proyectil entre las dos.
  * 
  */
 
+use crate::camera::Camera;
+use crate::physics::{PROJECTILE_SPEED, UNITS};
 use crate::sprite::Sprite;
-use stdweb::unstable::TryInto;
+use crate::random::RandomSource;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum ProjectileDirection {
 	Down,
 	Left,
@@ -41,6 +50,7 @@ pub struct Projectile {
 	pub spte: Sprite,
 	x_max: i32,
 	y_max: i32,
+	last: (i32, i32),
 }
 
 impl Projectile {
@@ -57,20 +67,35 @@ impl Projectile {
 			spte,
 			x_max: 0,
 			y_max: 0,
+			last: (0, 0),
+		}
+	}
+
+	pub fn from_parts(spte: &Sprite, coord: ProjectileCoord, x_max: i32, y_max: i32) -> Projectile {
+		Projectile {
+			last: (coord.x, coord.y),
+			coord,
+			spte: spte.deref(),
+			x_max,
+			y_max,
 		}
 	}
 
-	pub fn shoot_at(&mut self, x: u32, y: u32, x_max: u32, y_max: u32, kill: bool) {
+	pub fn bounds(&self) -> (i32, i32) {
+		(self.x_max, self.y_max)
+	}
+
+	pub fn last_coord(&self) -> (i32, i32) {
+		self.last
+	}
+
+	pub fn shoot_at(&mut self, x: u32, y: u32, x_max: u32, y_max: u32, kill: bool, random: &mut dyn RandomSource) {
 		let x_max = x_max as i32;
 		let y_max = y_max as i32;
 		let x = x as i32;
 		let y = y as i32;
-		let rand_dir:i8 = js!(return Math.floor(Math.random() * 3))
-			.try_into()
-			.unwrap();
-		let rand_y:i32 = js!(return Math.floor(Math.random() * 6))
-			.try_into()
-			.unwrap();
+		let rand_dir = random.range(3) as i8;
+		let rand_y = random.range((y_max - 4).max(1) as u32) as i32;	// Las manzanas pasan entre la fila 2 y la antepenúltima
 		self.x_max = x_max;
 		self.y_max = y_max;
 		self.coord = if kill {
@@ -78,7 +103,7 @@ impl Projectile {
 				1 => {
 					let mut x_in=0;
 					let mut y_in=0;
-					for i in 0..10 {
+					for i in 0..x_max.max(y_max) {
 						if x-i<1 || y-i<2 {
 							x_in = x-i;
 							y_in = y-i;
@@ -94,7 +119,7 @@ impl Projectile {
 				2 => {
 					let mut x_in=0;
 					let mut y_in=0;
-					for i in 0..10 {
+					for i in 0..x_max.max(y_max) {
 						if x+i==x_max-1 || y-i<2 {
 							x_in = x+i;
 							y_in = y-i;
@@ -108,7 +133,7 @@ impl Projectile {
 					}
 				},
 				_ => ProjectileCoord{
-					x: x,
+					x,
 					y: 1,
 					dir: ProjectileDirection::Down,
 				},
@@ -126,30 +151,75 @@ impl Projectile {
 					dir: ProjectileDirection::Left,
 				},
 			}
-		}
+		};
+		self.coord.x *= UNITS;	// Se dispara desde el inicio de una celda
+		self.coord.y *= UNITS;
+		self.last = (self.coord.x, self.coord.y);
+	}
+
+	pub fn to_world(&mut self, camera: &Camera) {
+		self.coord.x = camera.to_world((self.coord.x / UNITS).max(0) as u32) as i32 * UNITS;
+		self.x_max = camera.world_width as i32;
+		self.last = (self.coord.x, self.coord.y);
 	}
 
 	pub fn next_coord(&mut self) -> bool {
 		let mut result = true;
+		let (x, y) = (self.coord.x, self.coord.y);
+		let x_max = self.x_max * UNITS;
+		let y_max = (self.y_max - 1) * UNITS;
+		self.last = (x, y);
 		match self.coord.dir {
 			ProjectileDirection::Down => {
-				self.coord.y = if self.coord.y+1==self.y_max { result=false; self.coord.y } else { self.coord.y+1 };
+				self.coord.y = if y+PROJECTILE_SPEED>y_max { result=false; y } else { y+PROJECTILE_SPEED };
 			},
 			ProjectileDirection::Left => {
-				self.coord.x = if self.coord.x-1<0 { result=false; self.coord.x } else { self.coord.x-1 };
+				self.coord.x = if x-PROJECTILE_SPEED<0 { result=false; x } else { x-PROJECTILE_SPEED };
 			},
 			ProjectileDirection::Right => {
-				self.coord.x = if self.coord.x+1>self.x_max { result=false; self.coord.x } else { self.coord.x+1 };
+				self.coord.x = if x+PROJECTILE_SPEED>x_max { result=false; x } else { x+PROJECTILE_SPEED };
 			},
 			ProjectileDirection::DownLeft => {
-				self.coord.x = if self.coord.x-1<0 { result=false; self.coord.x } else { self.coord.x-1 };
-				self.coord.y = if self.coord.y+1==self.y_max { result=false; self.coord.y } else { self.coord.y+1 };
+				self.coord.x = if x-PROJECTILE_SPEED<0 { result=false; x } else { x-PROJECTILE_SPEED };
+				self.coord.y = if y+PROJECTILE_SPEED>y_max { result=false; y } else { y+PROJECTILE_SPEED };
 			},
 			ProjectileDirection::DownRight => {
-				self.coord.x = if self.coord.x+1>self.x_max { result=false; self.coord.x } else { self.coord.x+1 };
-				self.coord.y = if self.coord.y+1==self.y_max { result=false; self.coord.y } else { self.coord.y+1 };
+				self.coord.x = if x+PROJECTILE_SPEED>x_max { result=false; x } else { x+PROJECTILE_SPEED };
+				self.coord.y = if y+PROJECTILE_SPEED>y_max { result=false; y } else { y+PROJECTILE_SPEED };
 			},
 		};
 		result
 	}
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::random::XorShift;
+
+	fn shots(random: &mut dyn RandomSource, kill: bool) -> Vec<(i32, i32, ProjectileDirection)> {	// Diez disparos desde el centro de un mapa de 20x10
+		let spte = Sprite::new(Vec::new(), (0, 0, 0));
+		(0..10).map(|_| {
+			let mut proj = Projectile::new(&spte);
+			proj.shoot_at(10, 8, 20, 10, kill, random);
+			(proj.coord.x, proj.coord.y, proj.coord.dir)
+		}).collect()
+	}
+
+	#[test]
+	fn same_seed_shoots_the_same_projectiles() {
+		for kill in [true, false] {
+			let first = shots(&mut XorShift::new(42), kill);
+			assert_eq!(first, shots(&mut XorShift::new(42), kill));
+			assert_ne!(first, shots(&mut XorShift::new(43), kill));
+		}
+	}
+
+	#[test]
+	fn saved_state_continues_the_sequence() {
+		let mut random = XorShift::new(7);
+		shots(&mut random, true);
+		let mut restored = XorShift::from_state(random.get_state().unwrap());
+		assert_eq!(shots(&mut random, true), shots(&mut restored, true));
+		assert_eq!(random.next_u64(), restored.next_u64());
+	}
+}
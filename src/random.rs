@@ -0,0 +1,72 @@
+/*
+ * Fuente de números aleatorios del juego. Los proyectiles (rocas de lava y manzanas) se disparan con
+ * direcciones y alturas aleatorias, por lo que el jugador recibe una fuente que implemente RandomSource
+ * en lugar de depender de Math.random() del navegador.
+ *
+ * Por defecto se usa XorShift, un generador pseudoaleatorio xorshift64* que parte de una semilla, de modo
+ * que la misma semilla y la misma secuencia de teclas producen siempre el mismo juego. La semilla se mezcla
+ * con splitmix64 para que semillas parecidas (0, 1, 2...) generen secuencias distintas y para evitar el
+ * estado 0, en el cual xorshift sólo regresaría ceros.
+ */
+
+use std::fmt;
+
+pub trait RandomSource: fmt::Debug {
+    fn next_u64(&mut self) -> u64;                                          // Siguiente número de 64 bits de la secuencia
+
+    fn next_f64(&mut self) -> f64 {                                         // Número en [0, 1) equivalente a Math.random()
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn range(&mut self, n: u32) -> u32 {                                    // Número entero en [0, n) equivalente a Math.floor(Math.random() * n)
+        (self.next_f64() * n as f64) as u32
+    }
+
+    fn get_state(&self) -> Option<u64> {                                    // Estado interno para guardarlo en una fotografía, None si no se puede guardar
+        None
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct XorShift {
+    seed: u64,
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        XorShift {
+            seed,
+            state: if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z },
+        }
+    }
+
+    pub fn from_state(state: u64) -> XorShift {                             // Continúa una secuencia a partir de un estado guardado con get_state
+        XorShift {
+            seed: 0,
+            state: if state == 0 { 0x9e37_79b9_7f4a_7c15 } else { state },
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RandomSource for XorShift {
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn get_state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * Definición de la interfaz que debe cumplir cualquier medio en el que se quiera dibujar el juego (el canvas
+ * del navegador, un buffer en memoria, una terminal, una ventana nativa...). El jugador sólo conoce esta
+ * interfaz, de modo que agregar una nueva forma de mostrar el juego no requiere modificar las reglas.
+ *
+ * Las coordenadas que reciben draw_sprite y draw_text están dadas en celdas del mapa (un sprite de 8x8 ocupa
+ * una celda), cada implementación se encarga de escalarlas a su propia resolución. Pueden tener fracciones de
+ * celda, así el zorro y los proyectiles se dibujan entre dos celdas mientras se mueven; las implementaciones
+ * que no tienen tanta resolución redondean al pixel más cercano. Un pixel del sprite mide
+ * siempre un octavo de celda, así que un sprite más grande ocupa varias celdas: su esquina inferior izquierda
+ * queda en la de la celda (x, y) y crece hacia arriba y hacia la derecha, como un árbol o un jefe que se
+ * apoya en el mismo suelo que el zorro. draw_map tiene una
+ * implementación por defecto que limpia el fondo y dibuja cada celda del mapa con draw_sprite, y draw_tiles
+ * dibuja sólo las celdas, para poner un mapa encima de otro (por ejemplo el mapa de pausa). Ambas reciben
+ * la cámara del nivel y sólo dibujan las columnas que se ven en pantalla.
+ *
+ * draw_graph recibe el diagrama de la máquina de estados ya acomodado (ver graph.rs), con posiciones
+ * relativas al tamaño del panel, así que ninguna implementación depende de cuáles estados existen.
+ */
+
+use crate::camera::Camera;
+use crate::gamemap::GameMap;
+use crate::graph::StateGraph;
+use crate::sprite::Sprite;
+
+pub trait Renderer {
+    fn clear(&mut self, bg: &str);                                          // Limpia toda la superficie con el color indicado
+
+    fn draw_sprite(&mut self, x: f64, y: f64, spte: &Sprite);               // Dibuja un sprite en la celda (x, y)
+
+    fn draw_text(&mut self, x: f64, y: f64, text: &str, color: &str);       // Escribe un texto del color indicado a partir de la celda (x, y)
+
+    fn draw_graph(&mut self, graph: &StateGraph);                           // Dibuja la máquina de estados resaltando el estado actual y la última transición
+
+    fn draw_map(&mut self, map: &GameMap, camera: &Camera) {                // Dibuja el fondo y las celdas visibles de un mapa
+        self.clear(
+            if map.bg_alter { map.bg_temp.as_ref() } else { map.bg.as_ref() }
+        );
+        self.draw_tiles(map, camera);
+    }
+
+    fn draw_tiles(&mut self, map: &GameMap, camera: &Camera) {              // Dibuja las celdas visibles de un mapa sin limpiar el fondo
+        for (i, row) in map.map.iter().enumerate() {
+            for j in 0..camera.width {
+                let spte = match row.get(camera.to_world(j) as usize) {
+                    Some(s) if *s != 0 => map.sprites.get(*s as usize - 1),
+                    _ => None,
+                };
+                if let Some(spte) = spte {                                  // Una celda sin sprite se deja vacía
+                    self.draw_sprite(j as f64, i as f64, spte);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+/*
+ * Grabación y reproducción de partidas. Mientras se juega, Recorder guarda cada evento del teclado junto con
+ * el número de updates que se habían ejecutado cuando ocurrió (Character::get_tick). Como los proyectiles se
+ * disparan con un generador que parte de una semilla, basta con guardar la semilla y la lista de eventos para
+ * reproducir la partida cuadro por cuadro: ReplayPlayer crea un jugador con la misma semilla y, antes de cada
+ * update, aplica los eventos que se grabaron en ese tick.
+ *
+ * También se guarda de dónde salieron los niveles (Campaign, ver CampaignSource en campaign.rs) y, si la
+ * partida usó --assets, el directorio de los archivos (Assets), para que ReplayPlayer arme la misma partida
+ * con los mismos mapas. Los archivos del mapa y del directorio se buscan con la ruta que se grabó, así que
+ * también se guarda una huella (FNV-1a de 64 bits) de cada archivo que usó la partida (File, ver
+ * file_hashes): ReplayPlayer::new se niega a reproducir si un archivo cambió o si el directorio tiene
+ * archivos con errores, en lugar de llegar a otro resultado.
+ *
+ * Al terminar la grabación también se guarda el tick final, el puntaje y el tick en que terminó la última
+ * partida, con lo que se puede comprobar que la reproducción llegó exactamente al mismo resultado.
+ *
+ * El archivo de repetición es de texto y sigue el estilo de los archivos de mapas:
+ *   FoxHellReplay 3
+ *   Seed:42
+ *   Campaign:endless 0 42
+ *   Assets:src/files
+ *   File:fox_sprites.txt 5b2c0e1f9a7d4c31
+ *   ...
+ *   EndTick:1830
+ *   Score:7
+ *   GameOverTick:1830
+ *   EventsStart
+ *   12 Start
+ *   40 LeftDown
+ *   EventsEnd
+ * GameOverTick puede valer None si la grabación terminó sin perder. Las repeticiones de la versión 1 no
+ * tienen Campaign ni Assets y se reproducen con los niveles y archivos incluidos en el binario; las de la
+ * versión 1 y 2 no tienen File y se reproducen sin revisar los archivos.
+ */
+
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use crate::assets::{self, Assets};
+use crate::campaign::{Campaign, CampaignSource};
+use crate::character::Character;
+use crate::input::Input;
+use crate::random::XorShift;
+
+pub const REPLAY_VERSION: u32 = 3;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct InputEvent {
+    pub tick: u64,
+    pub input: Input,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub campaign: CampaignSource,
+    pub assets: Option<String>,
+    pub files: Vec<(String, u64)>,                                          // Huella de cada archivo, vacía antes de la versión 3
+    pub events: Vec<InputEvent>,
+    pub end_tick: u64,
+    pub score: u16,
+    pub game_over_tick: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct ReplayResult {
+    pub end_tick: u64,
+    pub score: u16,
+    pub game_over_tick: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    seed: u64,
+    campaign: CampaignSource,
+    assets: Option<String>,
+    files: Vec<(String, u64)>,
+    events: Vec<InputEvent>,
+}
+
+impl Recorder {                                                             // La semilla debe ser la del jugador recién creado
+    pub fn new(seed: u64, character: &Character, assets: Option<String>) -> Recorder {
+        let campaign = character.get_campaign_source().clone();
+        Recorder {
+            seed,
+            files: file_hashes(character.assets(), &campaign),
+            campaign,
+            assets,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn apply(&mut self, character: &mut Character, input: Input) {     // Graba el evento y lo aplica al jugador
+        self.events.push(InputEvent {
+            tick: character.get_tick(),
+            input,
+        });
+        character.apply_input(input);
+    }
+
+    pub fn finish(&self, character: &Character) -> Replay {                // Genera la repetición con el resultado actual del jugador
+        Replay {
+            seed: self.seed,
+            campaign: self.campaign.clone(),
+            assets: self.assets.clone(),
+            files: self.files.clone(),
+            events: self.events.clone(),
+            end_tick: character.get_tick(),
+            score: character.get_score(),
+            game_over_tick: character.get_game_over_tick(),
+        }
+    }
+}
+
+impl Replay {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "FoxHellReplay {}", REPLAY_VERSION);
+        let _ = writeln!(text, "Seed:{}", self.seed);
+        let _ = writeln!(text, "Campaign:{}", self.campaign.to_text());
+        if let Some(dir) = self.assets.as_ref() {
+            let _ = writeln!(text, "Assets:{}", dir);
+        }
+        for (name, hash) in self.files.iter() {
+            let _ = writeln!(text, "File:{} {:016x}", name, hash);
+        }
+        let _ = writeln!(text, "EndTick:{}", self.end_tick);
+        let _ = writeln!(text, "Score:{}", self.score);
+        match self.game_over_tick {
+            Some(tick) => { let _ = writeln!(text, "GameOverTick:{}", tick); },
+            None => { let _ = writeln!(text, "GameOverTick:None"); },
+        }
+        let _ = writeln!(text, "EventsStart");
+        for event in self.events.iter() {
+            let _ = writeln!(text, "{} {}", event.tick, event.input.name());
+        }
+        let _ = writeln!(text, "EventsEnd");
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines().enumerate();
+
+        let version = match lines.next() {
+            Some((_, header)) => match header.trim().strip_prefix("FoxHellReplay ").map(|v| v.parse::<u32>()) {
+                Some(Ok(version)) if (1..=REPLAY_VERSION).contains(&version) => version,
+                _ => return Err(format!("línea 1: versión de repetición no soportada '{}'", header)),
+            },
+            None => return Err(String::from("archivo de repetición vacío")),
+        };
+
+        let mut replay = Replay {
+            seed: 0,
+            campaign: CampaignSource::Levels,
+            assets: None,
+            files: Vec::new(),
+            events: Vec::new(),
+            end_tick: 0,
+            score: 0,
+            game_over_tick: None,
+        };
+        let mut in_events = false;
+
+        for (i, line) in lines {
+            let line = line.trim();
+            let err = |reason: &str| Err(format!("línea {}: {} '{}'", i + 1, reason, line));
+            match line {
+                "" => {},
+                "EventsStart" => in_events = true,
+                "EventsEnd" => in_events = false,
+                _ if in_events => {
+                    let mut fields = line.split_whitespace();
+                    let tick = fields.next().and_then(|t| t.parse::<u64>().ok());
+                    let input = fields.next().and_then(Input::from_name);
+                    match (tick, input) {
+                        (Some(tick), Some(input)) => replay.events.push(InputEvent { tick, input }),
+                        _ => return err("evento inválido"),
+                    }
+                },
+                _ => {
+                    let mut fields = line.splitn(2, ':');
+                    let key = fields.next().unwrap_or("");
+                    let value = fields.next().unwrap_or("");
+                    let parsed = match key {
+                        "Seed" => value.parse().map(|v| replay.seed = v).is_ok(),
+                        "Campaign" if version >= 2 => CampaignSource::parse(value).map(|v| replay.campaign = v).is_some(),
+                        "Assets" if version >= 2 && !value.is_empty() => { replay.assets = Some(value.to_string()); true },
+                        "File" if version >= 3 => value.rsplit_once(' ')
+                            .and_then(|(name, hash)| Some((name.to_string(), u64::from_str_radix(hash, 16).ok()?)))
+                            .map(|file| replay.files.push(file)).is_some(),
+                        "EndTick" => value.parse().map(|v| replay.end_tick = v).is_ok(),
+                        "Score" => value.parse().map(|v| replay.score = v).is_ok(),
+                        "GameOverTick" if value == "None" => { replay.game_over_tick = None; true },
+                        "GameOverTick" => value.parse().map(|v| replay.game_over_tick = Some(v)).is_ok(),
+                        _ => return err("campo desconocido"),
+                    };
+                    if !parsed {
+                        return err("valor inválido");
+                    }
+                },
+            }
+        }
+
+        if replay.events.windows(2).any(|pair| pair[0].tick > pair[1].tick) {
+            return Err(String::from("los eventos no están ordenados por tick"));
+        }
+        Ok(replay)
+    }
+
+    pub fn result(&self) -> ReplayResult {                                  // Resultado que se grabó
+        ReplayResult {
+            end_tick: self.end_tick,
+            score: self.score,
+            game_over_tick: self.game_over_tick,
+        }
+    }
+
+    pub fn run(&self) -> Result<ReplayResult, String> {                     // Reproduce toda la partida sin dibujarla y regresa el resultado obtenido
+        let mut player = ReplayPlayer::new(self)?;
+        while player.step() {}
+        Ok(player.result())
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayPlayer<'a> {
+    replay: &'a Replay,
+    character: Character,
+    next_event: usize,
+}
+
+impl<'a> ReplayPlayer<'a> {
+    pub fn new(replay: &'a Replay) -> Result<ReplayPlayer<'a>, String> {    // Arma la partida que se grabó, con sus archivos
+        let mut assets = Assets::embedded();
+        if let Some(dir) = replay.assets.as_ref() {
+            let messages = assets.load_dir(Path::new(dir));
+            if !messages.is_empty() {                                       // Todos los errores, uno por línea
+                return Err(messages.join("\n"));
+            }
+        }
+        let files = file_hashes(&assets, &replay.campaign);
+        if let Some((name, _)) = replay.files.iter().find(|file| !files.contains(file)) {
+            return Err(format!("el archivo {} cambió desde que se grabó la repetición", name));
+        }
+        let campaign = Campaign::from_source(&replay.campaign, &assets)?;
+        let random = Box::new(XorShift::new(replay.seed));
+        Ok(ReplayPlayer {
+            replay,
+            character: Character::with_assets(campaign, assets, random),
+            next_event: 0,
+        })
+    }
+
+    pub fn character(&self) -> &Character {
+        &self.character
+    }
+
+    pub fn finished(&self) -> bool {
+        self.character.get_tick() >= self.replay.end_tick
+    }
+
+    pub fn step(&mut self) -> bool {                                        // Aplica los eventos del tick actual y ejecuta un update, false si ya terminó
+        if self.finished() {
+            return false;
+        }
+        let tick = self.character.get_tick();
+        while let Some(event) = self.replay.events.get(self.next_event) {
+            if event.tick > tick {
+                break;
+            }
+            self.character.apply_input(event.input);
+            self.next_event += 1;
+        }
+        self.character.update();
+        true
+    }
+
+    pub fn result(&self) -> ReplayResult {                                  // Resultado obtenido hasta el momento
+        ReplayResult {
+            end_tick: self.character.get_tick(),
+            score: self.character.get_score(),
+            game_over_tick: self.character.get_game_over_tick(),
+        }
+    }
+}
+
+                                                                            // Huella de los archivos que usa una partida: los de FILES, los mapas de
+                                                                            // levels.txt y el de --map, por nombre o ruta
+pub fn file_hashes(assets: &Assets, campaign: &CampaignSource) -> Vec<(String, u64)> {
+    let mut names: Vec<String> = assets::FILES.iter().map(|name| name.to_string()).collect();
+    names.extend(assets::referenced_maps(&assets.text("levels.txt")));
+    let mut files: Vec<(String, u64)> = names.into_iter().map(|name| {
+        let hash = fnv1a(&assets.text(&name));
+        (name, hash)
+    }).collect();
+    if let CampaignSource::Map(path) = campaign {
+        if let Ok(text) = fs::read_to_string(path) {
+            files.push((path.clone(), fnv1a(&text)));
+        }
+    }
+    files
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaParams;
+    use crate::random::RandomSource;
+
+    fn record(mut character: Character, seed: u64) -> Replay {             // Juega 3000 updates con teclas pseudoaleatorias fijas
+        let mut recorder = Recorder::new(seed, &character, None);
+        recorder.apply(&mut character, Input::Start);
+        let mut keys = XorShift::new(99);
+        for _ in 0..3000 {
+            let key = keys.next_u64();
+            if key.is_multiple_of(6) {
+                recorder.apply(&mut character, Input::ALL[(key >> 8) as usize % 7]);
+            }
+            character.update();
+        }
+        recorder.finish(&character)
+    }
+
+    #[test]
+    fn replay_reaches_the_recorded_result() {
+        let replay = record(Character::new(42), 42);
+        assert!(replay.game_over_tick.is_some());
+        assert_eq!(replay.run(), Ok(replay.result()));
+        assert_eq!(replay.run(), replay.run());
+    }
+
+    #[test]
+    fn replay_survives_the_text_format() {
+        let replay = record(Character::new(7), 7);
+        let text = replay.to_text();
+        assert!(text.starts_with("FoxHellReplay 3\nSeed:7\nCampaign:levels\nFile:fox_sprites.txt "));
+        assert_eq!(Replay::from_text(&text), Ok(replay.clone()));
+        assert_eq!(Replay::from_text(&text).unwrap().run(), Ok(replay.result()));
+    }
+
+    #[test]
+    fn replay_rebuilds_the_endless_arenas() {
+        let assets = Assets::embedded();
+        let campaign = Campaign::endless(5, &ArenaParams::default().with_difficulty(2), &assets);
+        let character = Character::with_assets(campaign, assets, Box::new(XorShift::new(5)));
+        let replay = record(character, 5);
+        assert_eq!(replay.campaign, CampaignSource::Endless { difficulty: 2, seeds: vec![5] });
+        assert_eq!(replay.run(), Ok(replay.result()));
+    }
+
+    #[test]
+    fn version_1_replays_use_the_default_levels() {
+        let text = "FoxHellReplay 1\nSeed:3\nEndTick:2\nScore:0\nGameOverTick:None\nEventsStart\n0 Start\nEventsEnd\n";
+        let replay = Replay::from_text(text).unwrap();
+        assert_eq!(replay.campaign, CampaignSource::Levels);
+        assert_eq!(replay.assets, None);
+        assert_eq!(replay.run(), Ok(replay.result()));
+
+        assert!(Replay::from_text(&text.replace("Seed:3", "Seed:3\nCampaign:levels")).is_err());
+        assert!(Replay::from_text(&text.replace("FoxHellReplay 1", "FoxHellReplay 4")).is_err());
+    }
+
+    #[test]
+    fn refuses_changed_or_broken_files() {
+        let mut replay = record(Character::new(11), 11);
+        assert_eq!(replay.files.len(), assets::FILES.len());
+        replay.files[0].1 ^= 1;
+        assert_eq!(replay.run(), Err(format!("el archivo {} cambió desde que se grabó la repetición", replay.files[0].0)));
+
+        let dir = std::env::temp_dir().join(format!("fox_hell_replay_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("map1.txt"), "MapStart\n000\n0000\nMapEnd\n").unwrap();
+        replay.files.clear();
+        replay.assets = Some(dir.display().to_string());
+        let result = replay.run();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.unwrap_err().contains("map1.txt"));
+    }
+}
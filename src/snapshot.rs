@@ -0,0 +1,766 @@
+/*
+ * Fotografía completa del estado de una sesión de juego, para poder suspenderla y reanudarla o para iniciar
+ * una partida desde una situación preparada a mano. Character::snapshot genera la fotografía y
+ * Character::from_snapshot reconstruye al jugador a partir de ella.
+ *
+ * Además de la información del jugador (coordenadas, estado, salto, teclas, puntaje, vidas...) se guardan los
+ * proyectiles en pantalla, el estado del generador de números aleatorios (para que la partida continúe igual
+ * que si no se hubiera interrumpido) y los tres mapas tal como están en memoria: las celdas, que cambian al
+ * consumir un MedKit o al escribir el puntaje final, y el mapa de bits de cada uno de sus sprites, que se
+ * invierten para animarse.
+ * Los mapas de bits se escriben igual que en los archivos de sprites (tres u64, o ANCHOxALTO.DATOS para los
+ * que no son de 8x8).
+ *
+ * El formato es de texto y sigue el estilo de los archivos de mapas, con una versión en la primera línea:
+ *   FoxHellSnapshot 8
+ *   GameState:Play
+ *   Campaign:endless 0 42 9184467440737095
+ *   Level:1 250 10
+ *   State:Walk Left
+ *   Coord:9 8
+ *   ...
+ *   ProjectilesStart
+ *   Ice 128 96 DownLeft 20 10 0-1536-0
+ *   ProjectilesEnd
+ *   TilesStart Play
+ *   70000000000000000007
+ *   ...
+ *   TilesEnd
+ *   BitmapsStart Play
+ *   0-15848903683080192-4055123684474625024
+ *   ...
+ *   BitmapsEnd
+ * Los mapas se identifican como Start, Play y End, y sus celdas usan los mismos caracteres que los archivos
+ * de mapas (0-9 y A-Z). Level guarda el nivel de la partida (empezando en 0), los updates que lleva en él y el
+ * puntaje con el que empezó; si no aparece se toma el primer nivel. Theme guarda el nombre del tema de colores
+ * (ver theme.rs); si no aparece o ya no existe se usa el Clásico. Durante la pausa las teclas que se presionan
+ * o liberan sólo se registran; PausedKeys guarda cuáles estaban presionadas al pausar, y al reanudar se
+ * aplican los cambios que hubo respecto a ese momento.
+ *
+ * Motion guarda la posición del zorro en unidades de sub-celda, su velocidad vertical y si la flecha ARRIBA
+ * está presionada (ver physics.rs); si no aparece el zorro queda en reposo en la celda de Coord. Las
+ * coordenadas de los proyectiles también están en unidades.
+ *
+ * Campaign guarda de dónde salieron los niveles de la partida (ver CampaignSource en campaign.rs), así una
+ * partida de --map o del modo sin fin se reanuda en el mismo nivel; si no aparece se usa levels.txt.
+ *
+ * Cada cambio del formato aumenta SNAPSHOT_VERSION, así un juego anterior rechaza la fotografía por su
+ * versión y no por un campo que no conoce. Las versiones son:
+ *   1  formato inicial: posición del zorro en celdas (Coord) con JumpH, las filas que llevaba subidas un
+ *      salto que subía una fila por update; proyectiles en celdas
+ *   2  PausedKeys y el estado Paused
+ *   3  la fila superior del mapa Play es la del nivel; antes se borraba para escribir ahí el HUD
+ *   4  Level y los estados LevelComplete y Victory
+ *   5  Theme
+ *   6  Motion:ALTO VELOCIDAD ARRIBA en lugar de JumpH, sin la posición horizontal
+ *   7  Motion con la posición horizontal en unidades y proyectiles en unidades
+ *   8  Campaign
+ * Las fotografías de versiones anteriores se convierten al leerlas: sin PausedKeys no hay teclas guardadas de
+ * la pausa, la fila superior del mapa Play se toma del nivel (Character::from_snapshot, con version), la
+ * partida sigue en el primer nivel con el tema Clásico, los proyectiles se pasan a unidades, un salto con
+ * JumpH continúa con la velocidad que lleva un salto completo después de subir esas filas (migrate_jump) y la
+ * partida es la de levels.txt. Una versión desconocida o más nueva que SNAPSHOT_VERSION se rechaza, igual que
+ * un campo que no pertenece a la versión del archivo.
+ *
+ * Una fotografía puede estar hecha a mano, así que Character::from_snapshot regresa un error (nunca entra en
+ * pánico) si las coordenadas, la posición o la velocidad del zorro quedan fuera del mapa, o si un proyectil
+ * tiene límites distintos del tamaño del mapa o una posición fuera de ellos.
+ */
+
+use std::fmt::Write;
+
+use crate::bitmap::BitMap;
+use crate::campaign::CampaignSource;
+use crate::direction::Direction;
+use crate::gamestate::GameState;
+use crate::physics::{HELD_GRAVITY, JUMP_SPEED, UNITS};
+use crate::projectile::ProjectileDirection;
+use crate::state::State;
+
+pub const SNAPSHOT_VERSION: u32 = 8;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum ProjectileKind {
+    Ice,
+    Apple,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ProjectileSnapshot {
+    pub kind: ProjectileKind,
+    pub x: i32,
+    pub y: i32,
+    pub dir: ProjectileDirection,
+    pub x_max: i32,
+    pub y_max: i32,
+    pub bitmap: BitMap,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct MapSnapshot {
+    pub tiles: Vec<Vec<u8>>,
+    pub bitmaps: Vec<BitMap>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Snapshot {
+    pub version: u32,                                                       // Versión del archivo leído, SNAPSHOT_VERSION en una fotografía nueva
+    pub width: u32,
+    pub height: u32,
+    pub game_state: GameState,
+    pub campaign: CampaignSource,
+    pub level: usize,
+    pub level_tick: u64,
+    pub level_score: u16,
+    pub state: State,
+    pub last_state: State,
+    pub coord: (u32, u32),
+    pub dir: Direction,
+    pub last_dir: Direction,
+    pub it: usize,
+    pub tick: u64,
+    pub game_over_tick: Option<u64>,
+    pub motion: Option<(i32, i32, i32, bool)>,                              // Posición en unidades, velocidad vertical y flecha ARRIBA
+    pub left_key: bool,
+    pub right_key: bool,
+    pub down_key: bool,
+    pub paused_keys: (bool, bool, bool),
+    pub score: u16,
+    pub lifes: u8,
+    pub random: Option<u64>,
+    pub projectiles: Vec<ProjectileSnapshot>,
+    pub map_start: MapSnapshot,
+    pub map: MapSnapshot,
+    pub map_end: MapSnapshot,
+    pub theme: String,
+}
+
+impl Snapshot {
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "FoxHellSnapshot {}", SNAPSHOT_VERSION);
+        let _ = writeln!(text, "Size:{} {}", self.width, self.height);
+        let _ = writeln!(text, "GameState:{}", game_state_name(self.game_state));
+        let _ = writeln!(text, "Campaign:{}", self.campaign.to_text());
+        let _ = writeln!(text, "Level:{} {} {}", self.level, self.level_tick, self.level_score);
+        let _ = writeln!(text, "State:{}", state_name(self.state));
+        let _ = writeln!(text, "LastState:{}", state_name(self.last_state));
+        let _ = writeln!(text, "Coord:{} {}", self.coord.0, self.coord.1);
+        let _ = writeln!(text, "Dir:{}", direction_name(self.dir));
+        let _ = writeln!(text, "LastDir:{}", direction_name(self.last_dir));
+        let _ = writeln!(text, "It:{}", self.it);
+        let _ = writeln!(text, "Tick:{}", self.tick);
+        let _ = writeln!(text, "GameOverTick:{}", optional(self.game_over_tick));
+        if let Some((pos_x, pos_y, vel_y, up)) = self.motion {
+            let _ = writeln!(text, "Motion:{} {} {} {}", pos_x, pos_y, vel_y, up as u8);
+        }
+        let _ = writeln!(text, "Keys:{} {} {}", self.left_key as u8, self.right_key as u8, self.down_key as u8);
+        let (left, right, down) = self.paused_keys;
+        let _ = writeln!(text, "PausedKeys:{} {} {}", left as u8, right as u8, down as u8);
+        let _ = writeln!(text, "Score:{}", self.score);
+        let _ = writeln!(text, "Lifes:{}", self.lifes);
+        let _ = writeln!(text, "Random:{}", optional(self.random));
+        let _ = writeln!(text, "Theme:{}", self.theme);
+
+        let _ = writeln!(text, "ProjectilesStart");
+        for proj in self.projectiles.iter() {
+            let kind = match proj.kind {
+                ProjectileKind::Ice => "Ice",
+                ProjectileKind::Apple => "Apple",
+            };
+            let _ = writeln!(text, "{} {} {} {:?} {} {} {}",
+                kind, proj.x, proj.y, proj.dir, proj.x_max, proj.y_max, proj.bitmap.to_text());
+        }
+        let _ = writeln!(text, "ProjectilesEnd");
+
+        for (name, map) in [("Start", &self.map_start), ("Play", &self.map), ("End", &self.map_end)].iter() {
+            let _ = writeln!(text, "TilesStart {}", name);
+            for row in map.tiles.iter() {
+                let line: String = row.iter().map(|t| tile_char(*t)).collect();
+                let _ = writeln!(text, "{}", line);
+            }
+            let _ = writeln!(text, "TilesEnd");
+            let _ = writeln!(text, "BitmapsStart {}", name);
+            for bitmap in map.bitmaps.iter() {
+                let _ = writeln!(text, "{}", bitmap.to_text());
+            }
+            let _ = writeln!(text, "BitmapsEnd");
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Snapshot, String> {
+        let mut lines = text.lines().enumerate();
+
+        let version = match lines.next() {
+            Some((_, header)) => match header.trim().strip_prefix("FoxHellSnapshot ").map(|v| v.parse::<u32>()) {
+                Some(Ok(version)) if (1..=SNAPSHOT_VERSION).contains(&version) => version,
+                Some(Ok(version)) if version > SNAPSHOT_VERSION => return Err(format!(
+                    "línea 1: la fotografía es de la versión {}, este juego lee hasta la {}", version, SNAPSHOT_VERSION)),
+                _ => return Err(format!("línea 1: versión de fotografía no soportada '{}'", header)),
+            },
+            None => return Err(String::from("fotografía vacía")),
+        };
+        let scale = if version < 7 { UNITS } else { 1 };                    // Antes de la versión 7 los proyectiles estaban en celdas
+        let mut motion: Option<(Option<i32>, i32, i32, bool)> = None;
+        let mut jump_h = 0;
+
+        let mut snapshot = Snapshot {
+            version,
+            width: 20,
+            height: 10,
+            game_state: GameState::Start,
+            campaign: CampaignSource::Levels,
+            level: 0,
+            level_tick: 0,
+            level_score: 0,
+            state: State::Stand,
+            last_state: State::Stand,
+            coord: (0, 0),
+            dir: Direction::Right,
+            last_dir: Direction::Right,
+            it: 0,
+            tick: 0,
+            game_over_tick: None,
+            motion: None,
+            left_key: false,
+            right_key: false,
+            down_key: false,
+            paused_keys: (false, false, false),
+            score: 0,
+            lifes: 0,
+            random: None,
+            projectiles: Vec::new(),
+            map_start: MapSnapshot::default(),
+            map: MapSnapshot::default(),
+            map_end: MapSnapshot::default(),
+            theme: String::new(),
+        };
+
+        let mut section: Option<(&str, String)> = None;
+        for (i, line) in lines {
+            let line = line.trim();
+            let fail = |reason: &str| format!("línea {}: {} '{}'", i + 1, reason, line);
+
+            if line.is_empty() {
+                continue;
+            }
+            if line == "ProjectilesEnd" || line == "TilesEnd" || line == "BitmapsEnd" {
+                section = None;
+                continue;
+            }
+            if line == "ProjectilesStart" {
+                section = Some(("Projectiles", String::new()));
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("TilesStart ") {
+                section = Some(("Tiles", name.to_string()));
+                continue;
+            }
+            if let Some(name) = line.strip_prefix("BitmapsStart ") {
+                section = Some(("Bitmaps", name.to_string()));
+                continue;
+            }
+
+            match section.as_ref() {
+                Some(("Projectiles", _)) => {
+                    let mut proj = parse_projectile(line).ok_or_else(|| fail("proyectil inválido"))?;
+                    proj.x *= scale;
+                    proj.y *= scale;
+                    snapshot.projectiles.push(proj);
+                },
+                Some((kind, name)) => {
+                    let map = match name.as_ref() {
+                        "Start" => &mut snapshot.map_start,
+                        "Play" => &mut snapshot.map,
+                        "End" => &mut snapshot.map_end,
+                        _ => return Err(fail("mapa desconocido")),
+                    };
+                    if *kind == "Tiles" {
+                        let row = line.chars().map(tile_index).collect::<Option<Vec<u8>>>()
+                            .ok_or_else(|| fail("celda inválida"))?;
+                        map.tiles.push(row);
+                    } else {
+                        map.bitmaps.push(BitMap::from_text(line).ok().ok_or_else(|| fail("mapa de bits inválido"))?);
+                    }
+                },
+                None => {
+                    let mut fields = line.splitn(2, ':');
+                    let key = fields.next().unwrap_or("");
+                    let value = fields.next().unwrap_or("").trim();
+                    let ok = match key {
+                        "Size" => parse_pair(value).map(|(w, h)| { snapshot.width = w; snapshot.height = h; }).is_some(),
+                        "GameState" => parse_game_state(value)
+                            .filter(|state| *state != GameState::Paused || version >= 2)
+                            .filter(|state| !matches!(state, GameState::LevelComplete | GameState::Victory) || version >= 4)
+                            .map(|v| snapshot.game_state = v).is_some(),
+                        "Campaign" if version >= 8 => CampaignSource::parse(value).map(|v| snapshot.campaign = v).is_some(),
+                        "Level" if version >= 4 => parse_level(value).map(|(l, t, s)| {
+                            snapshot.level = l;
+                            snapshot.level_tick = t;
+                            snapshot.level_score = s;
+                        }).is_some(),
+                        "State" => parse_state(value).map(|v| snapshot.state = v).is_some(),
+                        "LastState" => parse_state(value).map(|v| snapshot.last_state = v).is_some(),
+                        "Coord" => parse_pair(value).map(|v| snapshot.coord = v).is_some(),
+                        "Dir" => parse_direction(value).map(|v| snapshot.dir = v).is_some(),
+                        "LastDir" => parse_direction(value).map(|v| snapshot.last_dir = v).is_some(),
+                        "It" => value.parse().map(|v| snapshot.it = v).is_ok(),
+                        "Tick" => value.parse().map(|v| snapshot.tick = v).is_ok(),
+                        "GameOverTick" => parse_optional(value).map(|v| snapshot.game_over_tick = v).is_some(),
+                        "Motion" if version >= 6 => parse_motion(value)     // Sin la posición horizontal sólo en la versión 6
+                            .filter(|(pos_x, _, _, _)| pos_x.is_some() == (version >= 7))
+                            .map(|v| motion = Some(v)).is_some(),
+                        "JumpH" if version < 6 => value.parse().map(|v| jump_h = v).is_ok(),
+                        "Keys" => parse_keys(value).map(|(l, r, d)| {
+                            snapshot.left_key = l;
+                            snapshot.right_key = r;
+                            snapshot.down_key = d;
+                        }).is_some(),
+                        "PausedKeys" if version >= 2 => parse_keys(value).map(|keys| snapshot.paused_keys = keys).is_some(),
+                        "Score" => value.parse().map(|v| snapshot.score = v).is_ok(),
+                        "Lifes" => value.parse().map(|v| snapshot.lifes = v).is_ok(),
+                        "Random" => parse_optional(value).map(|v| snapshot.random = v).is_some(),
+                        "Theme" if version >= 5 => { snapshot.theme = value.to_string(); true },
+                        _ => return Err(fail("campo desconocido")),
+                    };
+                    if !ok {
+                        return Err(fail("valor inválido"));
+                    }
+                },
+            }
+        }
+        snapshot.motion = motion.map(|(pos_x, pos_y, vel_y, up)| {      // En la versión 6 no se guardaba la posición horizontal
+            (pos_x.unwrap_or(snapshot.coord.0 as i32 * UNITS), pos_y, vel_y, up)
+        });
+        if let (None, State::Jump(_)) = (snapshot.motion, snapshot.state) {
+            if jump_h > 0 {
+                snapshot.motion = Some(migrate_jump(snapshot.coord, jump_h));
+            }
+        }
+        Ok(snapshot)
+    }
+}
+                                                                            // Motion de un salto anterior a la versión 6 que llevaba jump_h filas subidas
+fn migrate_jump(coord: (u32, u32), jump_h: u8) -> (i32, i32, i32, bool) {
+    let mut vel_y = -JUMP_SPEED;
+    let mut risen = 0;
+    while vel_y < 0 && risen < jump_h as i32 * UNITS {
+        risen -= vel_y;
+        vel_y += HELD_GRAVITY;
+    }
+    (coord.0 as i32 * UNITS, coord.1 as i32 * UNITS, vel_y, true)
+}
+
+pub fn state_name(state: State) -> String {
+    match state {
+        State::Stand => String::from("Stand"),
+        State::Walk(dir) => format!("Walk {}", direction_name(dir)),
+        State::Jump(dir) => format!("Jump {}", direction_name(dir)),
+        State::Fall(dir) => format!("Fall {}", direction_name(dir)),
+        State::Meteor => String::from("Meteor"),
+        State::Crouch => String::from("Crouch"),
+    }
+}
+
+pub fn parse_state(text: &str) -> Option<State> {
+    let mut fields = text.split_whitespace();
+    let name = fields.next()?;
+    let dir = fields.next().map(parse_direction);
+    match (name, dir) {
+        ("Stand", None) => Some(State::Stand),
+        ("Meteor", None) => Some(State::Meteor),
+        ("Crouch", None) => Some(State::Crouch),
+        ("Walk", Some(Some(dir))) => Some(State::Walk(dir)),
+        ("Jump", Some(Some(dir))) => Some(State::Jump(dir)),
+        ("Fall", Some(Some(dir))) => Some(State::Fall(dir)),
+        _ => None,
+    }
+}
+
+fn direction_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Left => "Left",
+        Direction::Right => "Right",
+        Direction::None => "None",
+    }
+}
+
+fn parse_direction(text: &str) -> Option<Direction> {
+    match text {
+        "Left" => Some(Direction::Left),
+        "Right" => Some(Direction::Right),
+        "None" => Some(Direction::None),
+        _ => None,
+    }
+}
+
+fn game_state_name(state: GameState) -> &'static str {
+    match state {
+        GameState::Start => "Start",
+        GameState::Play => "Play",
+        GameState::Paused => "Paused",
+        GameState::GameOver => "GameOver",
+        GameState::LevelComplete => "LevelComplete",
+        GameState::Victory => "Victory",
+    }
+}
+
+fn parse_game_state(text: &str) -> Option<GameState> {
+    match text {
+        "Start" => Some(GameState::Start),
+        "Play" => Some(GameState::Play),
+        "Paused" => Some(GameState::Paused),
+        "GameOver" => Some(GameState::GameOver),
+        "LevelComplete" => Some(GameState::LevelComplete),
+        "Victory" => Some(GameState::Victory),
+        _ => None,
+    }
+}
+
+fn parse_projectile_direction(text: &str) -> Option<ProjectileDirection> {
+    match text {
+        "Down" => Some(ProjectileDirection::Down),
+        "Left" => Some(ProjectileDirection::Left),
+        "Right" => Some(ProjectileDirection::Right),
+        "DownLeft" => Some(ProjectileDirection::DownLeft),
+        "DownRight" => Some(ProjectileDirection::DownRight),
+        _ => None,
+    }
+}
+
+fn parse_projectile(line: &str) -> Option<ProjectileSnapshot> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 7 {
+        return None;
+    }
+    let kind = match fields[0] {
+        "Ice" => ProjectileKind::Ice,
+        "Apple" => ProjectileKind::Apple,
+        _ => return None,
+    };
+    Some(ProjectileSnapshot {
+        kind,
+        x: fields[1].parse().ok()?,
+        y: fields[2].parse().ok()?,
+        dir: parse_projectile_direction(fields[3])?,
+        x_max: fields[4].parse().ok()?,
+        y_max: fields[5].parse().ok()?,
+        bitmap: BitMap::from_text(fields[6]).ok()?,
+    })
+}
+
+fn parse_pair(text: &str) -> Option<(u32, u32)> {
+    let mut fields = text.split_whitespace();
+    let a = fields.next()?.parse().ok()?;
+    let b = fields.next()?.parse().ok()?;
+    Some((a, b))
+}
+
+fn parse_keys(text: &str) -> Option<(bool, bool, bool)> {
+    let keys: Vec<&str> = text.split_whitespace().collect();
+    match keys.as_slice() {
+        [l, r, d] => Some((*l == "1", *r == "1", *d == "1")),
+        _ => None,
+    }
+}
+
+fn parse_motion(text: &str) -> Option<(Option<i32>, i32, i32, bool)> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    match fields.as_slice() {
+        [pos_x, pos_y, vel_y, up] => Some((Some(pos_x.parse().ok()?), pos_y.parse().ok()?, vel_y.parse().ok()?, *up == "1")),
+        [pos_y, vel_y, up] => Some((None, pos_y.parse().ok()?, vel_y.parse().ok()?, *up == "1")),
+        _ => None,
+    }
+}
+
+fn parse_level(text: &str) -> Option<(usize, u64, u16)> {
+    let mut fields = text.split_whitespace();
+    let level = fields.next()?.parse().ok()?;
+    let tick = fields.next()?.parse().ok()?;
+    let score = fields.next()?.parse().ok()?;
+    Some((level, tick, score))
+}
+
+fn optional(value: Option<u64>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => String::from("None"),
+    }
+}
+
+fn parse_optional(text: &str) -> Option<Option<u64>> {
+    if text == "None" {
+        return Some(None);
+    }
+    text.parse().ok().map(Some)
+}
+
+fn tile_char(tile: u8) -> char {
+    match tile {
+        0..=9 => (b'0' + tile) as char,
+        _ => (b'A' + tile - 10) as char,
+    }
+}
+
+fn tile_index(c: char) -> Option<u8> {
+    if c.is_ascii_digit() {
+        Some(c as u8 - b'0')
+    } else if c.is_ascii_uppercase() {
+        Some(c as u8 - b'A' + 10)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaParams;
+    use crate::assets::Assets;
+    use crate::campaign::Campaign;
+    use crate::character::Character;
+    use crate::input::Input;
+    use crate::physics::{jump_height, rise, MAX_JUMP_H};
+    use crate::random::XorShift;
+
+    fn mid_game(mut character: Character) -> Character {               // Partida con saltos, proyectiles en pantalla y algunos updates
+        character.apply_input(Input::Start);
+        for i in 0..400 {
+            if i % 30 == 0 {
+                character.apply_input(Input::UpDown);
+            }
+            character.update();
+        }
+        character
+    }
+
+    fn check_round_trip(mut character: Character) {                     // La copia debe escribirse igual y seguir igual que el original
+        let text = character.snapshot().to_text();
+        let snapshot = Snapshot::from_text(&text).unwrap();
+        assert_eq!(snapshot, character.snapshot());
+
+        let mut copy = Character::from_snapshot(&snapshot).unwrap();
+        assert_eq!(copy.snapshot().to_text(), text);
+        for _ in 0..300 {
+            character.update();
+            copy.update();
+        }
+        assert_eq!(copy.snapshot(), character.snapshot());
+    }
+
+    #[test]
+    fn levels_game_round_trips() {
+        let character = mid_game(Character::new(5));
+        assert_eq!(character.snapshot().campaign, CampaignSource::Levels);
+        check_round_trip(character);
+    }
+
+    #[test]
+    fn endless_game_round_trips() {
+        let assets = Assets::embedded();
+        let campaign = Campaign::endless(7, &ArenaParams::default().with_difficulty(2), &assets);
+        let character = mid_game(Character::with_assets(campaign, assets, Box::new(XorShift::new(7))));
+        assert_eq!(character.snapshot().campaign, CampaignSource::Endless { difficulty: 2, seeds: vec![7] });
+        check_round_trip(character);
+    }
+
+    #[test]
+    fn map_game_round_trips() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/files/map1.txt");
+        let campaign = Campaign::from_file(path).unwrap();
+        let character = mid_game(Character::with_assets(campaign, Assets::embedded(), Box::new(XorShift::new(7))));
+        assert_eq!(character.snapshot().campaign, CampaignSource::Map(String::from(path)));
+        check_round_trip(character);
+    }
+
+    #[test]
+    fn crafted_snapshot_starts_mid_game() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        snapshot.score = 9;
+        snapshot.lifes = 1;
+        snapshot.projectiles.clear();
+        let character = Character::from_snapshot(&Snapshot::from_text(&snapshot.to_text()).unwrap()).unwrap();
+        assert_eq!(character.get_score(), 9);
+        assert_eq!(character.get_lifes(), 1);
+        assert!(character.get_ices().is_empty() && character.get_apples().is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_versions_and_fields() {
+        let text = Character::new(5).snapshot().to_text();
+        let newer = text.replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 99", 1);
+        assert!(Snapshot::from_text(&newer).is_err());
+        let unknown = text.replacen("\n", "\nBogus:1\n", 1);
+        assert!(Snapshot::from_text(&unknown).is_err());
+    }
+
+    #[test]
+    fn version_1_jumps_keep_rising() {
+        assert_eq!(migrate_jump((3, 5), 0), (3 * UNITS, 5 * UNITS, -JUMP_SPEED, true));
+        assert_eq!(migrate_jump((3, 5), 2), (3 * UNITS, 5 * UNITS, -JUMP_SPEED + 4 * HELD_GRAVITY, true));
+    }
+
+    #[test]
+    fn migrated_jumps_reach_the_full_height() {                         // Las filas ya subidas más lo que falta es un salto completo
+        for jump_h in 1..=MAX_JUMP_H {
+            let (_, start, mut vel_y, held) = migrate_jump((3, 8), jump_h);
+            let mut pos_y = start;
+            while vel_y < 0 {
+                (pos_y, vel_y) = rise(pos_y, vel_y, held);
+            }
+            let climbed = jump_h as i32 * UNITS + start - pos_y;
+            assert!(climbed <= jump_height() && climbed > jump_height() - JUMP_SPEED, "{} filas: {}", jump_h, climbed);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_values_without_panicking() {
+        let snapshot = mid_game(Character::new(5)).snapshot();
+        assert!(!snapshot.projectiles.is_empty());
+        let mut bad = Vec::new();
+
+        let mut coord = snapshot.clone();
+        coord.coord.1 = u32::MAX;
+        bad.push(coord);
+        for pos in [(i32::MAX, 0), (0, i32::MAX), (i32::MAX - UNITS / 4, i32::MAX - UNITS / 4), (-1, 0)] {
+            let mut motion = snapshot.clone();
+            motion.motion = Some((pos.0, pos.1, 0, false));
+            bad.push(motion);
+        }
+        let mut speed = snapshot.clone();
+        speed.motion = speed.motion.map(|(x, y, _, up)| (x, y, i32::MAX, up));
+        bad.push(speed);
+        for bounds in [(i32::MAX, snapshot.height as i32), (snapshot.width as i32, i32::MAX), (snapshot.width as i32 + 1, snapshot.height as i32)] {
+            let mut proj = snapshot.clone();
+            proj.projectiles[0].x_max = bounds.0;
+            proj.projectiles[0].y_max = bounds.1;
+            bad.push(proj);
+        }
+        for pos in [(i32::MAX, 0), (0, i32::MIN), (-1, 0), (snapshot.width as i32 * UNITS + 1, 0)] {
+            let mut proj = snapshot.clone();
+            proj.projectiles[0].x = pos.0;
+            proj.projectiles[0].y = pos.1;
+            bad.push(proj);
+        }
+
+        for snapshot in bad.iter() {
+            assert!(Character::from_snapshot(snapshot).is_err(), "{:?}", snapshot.motion);
+            assert!(Character::from_snapshot(&Snapshot::from_text(&snapshot.to_text()).unwrap()).is_err());
+        }
+    }
+
+    #[test]
+    fn version_1_has_no_paused_keys() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        snapshot.paused_keys = (true, false, true);
+        snapshot.game_state = GameState::Play;
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 1", 1);
+        let version_1 = |text: &str| text.lines()
+            .filter(|line| !line.starts_with("Campaign:") && !line.starts_with("PausedKeys:") && !line.starts_with("Level:")
+                && !line.starts_with("Theme:") && !line.starts_with("Motion:"))
+            .collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_1(&text)).unwrap();
+        assert_eq!(migrated.paused_keys, (false, false, false));
+        assert!(Snapshot::from_text(&version_1(&text).replacen("Keys:", "PausedKeys:1 0 1\nKeys:", 1)).is_err());
+        assert!(Snapshot::from_text(&version_1(&text).replacen("GameState:Play", "GameState:Paused", 1)).is_err());
+    }
+
+    #[test]
+    fn version_2_top_row_comes_from_the_level() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        let top = snapshot.map.tiles[0].clone();
+        snapshot.map.tiles[0][5] = 1;                                   // Una celda que el HUD escribía en el mapa
+        let current = Character::from_snapshot(&snapshot).unwrap();
+        assert_eq!(current.snapshot().map.tiles[0][5], 1);
+
+        snapshot.version = 2;
+        let migrated = Character::from_snapshot(&snapshot).unwrap();
+        assert_eq!(migrated.snapshot().map.tiles[0], top);
+    }
+
+    #[test]
+    fn version_3_starts_on_the_first_level() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        snapshot.game_state = GameState::Play;
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 3", 1);
+        let version_3 = text.lines()
+            .filter(|line| !line.starts_with("Campaign:") && !line.starts_with("Level:") && !line.starts_with("Theme:")
+                && !line.starts_with("Motion:"))
+            .collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_3).unwrap();
+        assert_eq!((migrated.level, migrated.level_tick, migrated.level_score), (0, 0, 0));
+        assert!(Snapshot::from_text(&version_3.replacen("GameState:", "Level:1 0 0\nGameState:", 1)).is_err());
+        assert!(Snapshot::from_text(&version_3.replacen("GameState:Play", "GameState:Victory", 1)).is_err());
+    }
+
+    #[test]
+    fn version_4_uses_the_classic_theme() {
+        let mut character = mid_game(Character::new(5));
+        assert!(character.themes().len() > 1);
+        character.set_theme(character.themes().len() - 1);
+        let mut snapshot = character.snapshot();
+        snapshot.game_state = GameState::Play;
+        snapshot.projectiles.clear();                                   // En la versión 4 estaban en celdas
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 4", 1);
+        let version_4 = text.lines()
+            .filter(|line| !line.starts_with("Campaign:") && !line.starts_with("Theme:") && !line.starts_with("Motion:"))
+            .collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_4).unwrap();
+        assert_eq!(Character::from_snapshot(&migrated).unwrap().theme().name, Character::new(5).theme().name);
+        assert!(Snapshot::from_text(&version_4.replacen("GameState:", &format!("Theme:{}\nGameState:", snapshot.theme), 1)).is_err());
+    }
+
+    #[test]
+    fn version_5_jumps_with_jump_h() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        snapshot.state = State::Jump(Direction::Right);
+        snapshot.projectiles.clear();
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 5", 1);
+        let version_5 = text.lines()
+            .filter(|line| !line.starts_with("Campaign:") && !line.starts_with("Motion:"))
+            .collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_5.replacen("Keys:", "JumpH:2\nKeys:", 1)).unwrap();
+        assert_eq!(migrated.motion, Some(migrate_jump(snapshot.coord, 2)));
+        assert!(Snapshot::from_text(&version_5.replacen("Keys:", "Motion:160 -10 1\nKeys:", 1)).is_err());
+
+        let version_6 = version_5.replacen("FoxHellSnapshot 5", "FoxHellSnapshot 6", 1);
+        let migrated = Snapshot::from_text(&version_6.replacen("Keys:", "Motion:160 -10 1\nKeys:", 1)).unwrap();
+        assert_eq!(migrated.motion, Some((snapshot.coord.0 as i32 * UNITS, 160, -10, true)));
+        assert!(Snapshot::from_text(&version_6.replacen("Keys:", "JumpH:2\nKeys:", 1)).is_err());
+        assert!(Snapshot::from_text(&version_6.replacen("Keys:", "Motion:96 160 -10 1\nKeys:", 1)).is_err());
+    }
+
+    #[test]
+    fn version_6_projectiles_move_to_units() {
+        let mut snapshot = mid_game(Character::new(5)).snapshot();
+        assert!(!snapshot.projectiles.is_empty());
+        for proj in snapshot.projectiles.iter_mut() {                   // En la versión 6 se guardaban en celdas
+            proj.x /= UNITS;
+            proj.y /= UNITS;
+        }
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 6", 1);
+        let version_6 = text.lines()
+            .filter(|line| !line.starts_with("Campaign:") && !line.starts_with("Motion:"))
+            .collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_6).unwrap();
+        for (old, new) in snapshot.projectiles.iter().zip(migrated.projectiles.iter()) {
+            assert_eq!((new.x, new.y), (old.x * UNITS, old.y * UNITS));
+        }
+        assert!(Character::from_snapshot(&migrated).is_ok());
+    }
+
+    #[test]
+    fn version_7_plays_levels_txt() {
+        let assets = Assets::embedded();
+        let campaign = Campaign::endless(7, &ArenaParams::default().with_difficulty(2), &assets);
+        let mut snapshot = mid_game(Character::with_assets(campaign, assets, Box::new(XorShift::new(7)))).snapshot();
+        snapshot.level = 0;                                             // levels.txt puede tener menos niveles que la partida
+        let text = snapshot.to_text().replacen(&format!("FoxHellSnapshot {}", SNAPSHOT_VERSION), "FoxHellSnapshot 7", 1);
+        assert!(Snapshot::from_text(&text).unwrap_err().contains("Campaign"));  // Campaign no existía en la versión 7
+        let version_7 = text.lines().filter(|line| !line.starts_with("Campaign:")).collect::<Vec<_>>().join("\n");
+        let migrated = Snapshot::from_text(&version_7).unwrap();
+        assert_eq!(migrated.campaign, CampaignSource::Levels);
+        assert_eq!(Character::from_snapshot(&migrated).unwrap().get_campaign_source(), &CampaignSource::Levels);
+    }
+}
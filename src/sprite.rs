@@ -4,24 +4,36 @@
  * en el mapa de bits.
  * 
  * Un sprite puede ser generado completamente con su versión en una línea, que contiene el nombre del sprite, la
- * lista de colores y los tres números u64 que conforman el mapa de bits, este se construye con BitMap.
+ * lista de colores y los tres números u64 que conforman el mapa de bits, este se construye con BitMap. Los
+ * sprites de otro tamaño que 8x8 usan la versión compacta del mapa de bits (ver bitmap.rs).
  * 
  * En esta clase también se definen métodos para costruir un sprite a partir de una línea que se importa de un archivo,
  * y también se define un método para generar un diccionario usando como index el nombre del sprite obtenido de la
- * línea importada y conteniedo un sprite por campo.
+ * línea importada y conteniedo un sprite por campo. Las dos revisan cada línea con validate.rs y regresan la
+ * columna (y la línea, en el diccionario) del primer error en lugar de armar un sprite incompleto; las líneas
+ * vacías se saltan.
  * 
  * El método deref() sirve para crear un duplicado de un sprite sin afectar a las reglas de Rust sobre Ownership.
+ *
+ * Un archivo de sprites (o la sección SpriteStart de un mapa) puede declarar paletas compartidas con una
+ * línea @Nombre:colores, así varios sprites usan los mismos colores sin repetirlos. En la lista de colores
+ * de un sprite, @Nombre se reemplaza por los colores de la paleta, que debe estar declarada antes, y se
+ * pueden agregar más colores después (@Fox-red). Un sprite puede tener hasta 255 colores si su mapa de
+ * bits usa 4 u 8 bits por celda.
  */
 
 use std::fmt;
 use std::string::String;
 use std::collections::HashMap;
 use crate::bitmap::BitMap;
+use crate::validate;
 
-#[derive(Debug)]
+pub type Palettes = HashMap<String, Vec<String>>;                           // Paletas compartidas por nombre
+
+#[derive(Debug, Clone)]
 pub struct Sprite {
     pub map: BitMap,
-    pub colorset: [String; 7],
+    pub colorset: Vec<String>,
 }
 
 impl fmt::Display for Sprite {
@@ -30,74 +42,137 @@ impl fmt::Display for Sprite {
             for n in line {
                 write!(f, "{}, ", n)?;
             }
-            writeln!(f, "")?;
+            writeln!(f)?;
         }
-        writeln!(f, "")
+        writeln!(f)
     }
 }
 
 impl Sprite {
     pub fn new(colors: Vec<String>, compressed_map: (u64, u64, u64)) -> Sprite {
-        let mut colorset = [String::new(),
-                            String::new(),
-                            String::new(),
-                            String::new(),
-                            String::new(),
-                            String::new(),
-                            String::new()
-        ];
-        for (i, color) in colors.iter().enumerate() {
-            colorset[i].push_str(color);
-        }
+        Sprite::with_map(colors, BitMap::from_compress(compressed_map))
+    }
+
+    pub fn with_map(colors: Vec<String>, map: BitMap) -> Sprite {
         Sprite {
-            map: BitMap::from_compress(compressed_map),
-            colorset,
+            map,
+            colorset: colors,
         }
     }
 
     pub fn deref(&self) -> Sprite {
         let mut colors: Vec<String> = Vec::new();
-        let compressed_map = self.map.compress_map();
 
         for color in self.colorset.iter() {
             colors.push(color.to_string());
         }
 
-        Sprite::new(colors, compressed_map)
+        Sprite::with_map(colors, self.map.clone())
     }
 
-    pub fn from_line(line: String) -> Sprite {
+    pub fn from_line(line: String) -> Result<Sprite, (usize, String)> {
+        Sprite::from_line_with(line, &Palettes::new())
+    }
+                                                                            // Igual que from_line, los colores pueden usar las paletas
+    pub fn from_line_with(line: String, palettes: &Palettes) -> Result<Sprite, (usize, String)> {
+        validate::check_sprite_line_with(&line, palettes)?;                 // La línea se revisa completa antes de armar el sprite
         let mut colors: Vec<String> = Vec::new();
-        let mut compressed_map: [u64; 3] = [0; 3];
 
         let mut data = line.split(":");
 
-        data.next().unwrap();
-    {
-        let clrs = data.next().unwrap();
+        let name = data.next().unwrap_or("");
+        let clrs = data.next().unwrap_or("");
         for color in clrs.split("-") {
-            colors.push(String::from(color));
+            match palettes.get(color.trim_start_matches('@')) {
+                Some(palette) if color.starts_with('@') => colors.extend(palette.iter().cloned()),
+                _ => colors.push(String::from(color)),
+            }
         }
+        let column = name.len() + clrs.len() + 3;
+        let map = BitMap::from_text(data.next().unwrap_or("")).map_err(|(offset, reason)| (column + offset, reason))?;
+
+        Ok(Sprite::with_map(colors, map))
     }
-    {
-        let compress = data.next().unwrap();
-        for (i, cmp) in compress.split("-").enumerate() {
-            compressed_map[i] = String::from(cmp).parse::<u64>().unwrap();
-        }
+
+    pub fn to_line(&self, name: &str) -> String {                          // Línea Nombre:colores:mapa de bits del sprite
+        format!("{}:{}:{}", name, self.colorset.join("-"), self.map.to_text())
     }
 
-        Sprite::new(colors, (compressed_map[0], compressed_map[1], compressed_map[2]) )
+    pub fn parse_palette(line: &str) -> Option<(String, Vec<String>)> {    // Línea @Nombre:colores de una paleta compartida
+        let mut data = line.strip_prefix('@')?.splitn(2, ':');
+        let name = data.next()?;
+        let colors = data.next()?.split('-').map(String::from).collect();
+        Some((String::from(name), colors))
     }
 
-    pub fn hash_from_text (text: String) -> HashMap<String, Sprite> {
+    pub fn hash_from_text(text: String) -> Result<HashMap<String, Sprite>, (usize, usize, String)> { // El error indica la línea y la columna
         let mut hash = HashMap::new();
+        let mut palettes = Palettes::new();
 
-        for line in text.lines() {
-            let key = line.split(":").next().unwrap();
-            let spte = Sprite::from_line(String::from(line));
+        for (i, line) in text.lines().enumerate() {
+            let fail = |(column, reason)| (i + 1, column, reason);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('@') {
+                validate::check_palette_line(line, &mut palettes).map_err(fail)?;
+                continue;
+            }
+            let key = line.split(":").next().unwrap_or("");
+            let spte = Sprite::from_line_with(String::from(line), &palettes).map_err(fail)?;
 
             hash.insert(String::from(key), spte);
         }
-        hash
+        Ok(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn colors(n: usize) -> Vec<String> {                                    // n colores distintos de CSS
+        (0..n).map(|i| format!("#{:06x}", i * 0x010203)).collect()
+    }
+
+    #[test]
+    fn deep_sprites_round_trip() {
+        for (max, depth) in [(12u8, "x4."), (200, "x8.")] {
+            let mut map = BitMap::with_size(16, 8);
+            map.0[3][5] = max;
+            map.0[7][15] = 1;
+            let spte = Sprite::with_map(colors(max as usize), map);
+            let line = spte.to_line("Boss");
+            assert!(line.contains(&format!("16x8{}", depth)), "{}", line);
+            let read = Sprite::from_line(line).unwrap();
+            assert_eq!((read.map, read.colorset), (spte.map, spte.colorset));
+        }
+    }
+
+    #[test]
+    fn shared_palettes_resolve() {
+        assert_eq!(Sprite::parse_palette("@Fox:red-white"), Some((String::from("Fox"), vec![String::from("red"), String::from("white")])));
+        assert_eq!(Sprite::parse_palette("Fox:red-white"), None);
+
+        let hash = Sprite::hash_from_text(String::from("@Fox:red-white\nStand:@Fox-black:0-0-7\nWalk:@Fox:0-0-3\n")).unwrap();
+        assert_eq!(hash["Stand"].colorset, ["red", "white", "black"]);
+        assert_eq!(hash["Walk"].colorset, ["red", "white"]);
+        assert_eq!(Sprite::hash_from_text(String::from("Stand:@Fox:0-0-1\n")).unwrap_err(), (1, 7, String::from("paleta desconocida 'Fox'")));
+    }
+
+    #[test]
+    fn rejects_indices_past_the_colors() {
+        let mut map = BitMap::with_size(8, 8);
+        map.0[0][0] = 3;
+        assert!(Sprite::from_line(Sprite::with_map(colors(3), map.clone()).to_line("Ok")).is_ok());
+        let line = Sprite::with_map(colors(2), map).to_line("Short");
+        assert_eq!(Sprite::from_line(line).unwrap_err().1, "el mapa de bits usa el color 3 y sólo hay 2");
+
+        let mut palettes = Palettes::new();
+        palettes.insert(String::from("Two"), colors(2));
+        let mut deep = BitMap::with_size(16, 16);
+        deep.0[15][15] = 20;
+        let line = format!("Boss:@Two:{}", deep.to_text());
+        assert!(Sprite::from_line_with(line, &palettes).unwrap_err().1.contains("color 20"));
     }
 }
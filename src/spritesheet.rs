@@ -0,0 +1,195 @@
+/*
+ * Conversión entre sprites e imágenes PNG, para dibujar los sprites con cualquier editor de pixeles en lugar
+ * de calcular a mano los tres u64 de cada mapa de bits. La usa el binario fox_hell_sprite.
+ *
+ * Al importar, cada pixel de la imagen es una celda del mapa de bits: los pixeles transparentes (alfa menor a
+ * 128) son el índice 0 y cada color distinto recibe el siguiente índice en el orden en que aparece, leyendo
+ * fila por fila. Se puede indicar una lista de colores para fijar los primeros índices (por ejemplo los de
+ * la paleta del zorro) y así obtener sprites compatibles entre sí. Las imágenes con paleta (PNG indexado),
+ * en escala de grises o RGB se convierten a RGBA antes de leerlas. El resultado es una línea
+ * Nombre:colores:mapa de bits con el mapa de bits escrito por BitMap::to_text (los tres u64 de compress_map
+ * si el sprite es de 8x8 y usa hasta 7 colores).
+ *
+ * Una hoja de sprites se divide con cells en celdas del mismo tamaño, de izquierda a derecha y de arriba
+ * abajo, y downscale lee una imagen exportada con escala. Al exportar, sheet hace lo contrario: pone los
+ * sprites en una fila de celdas del tamaño del sprite más grande, cada uno en la esquina inferior izquierda de
+ * su celda como se dibujan en el juego.
+ */
+
+use std::io::{self, Read, Write};
+
+use crate::bitmap::BitMap;
+use crate::color::{parse_color, Rgba};
+use crate::sprite::Sprite;
+
+const TRANSPARENT: Rgba = [0, 0, 0, 0];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<Rgba>,                                                  // Fila por fila
+}
+
+impl Image {
+    pub fn new(width: u32, height: u32) -> Image {                          // Imagen transparente
+        Image {
+            width,
+            height,
+            pixels: vec![TRANSPARENT; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> Rgba {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Rgba) {
+        if x < self.width && y < self.height {
+            self.pixels[(y * self.width + x) as usize] = color;
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {                                        // Verdadero si todos los pixeles son transparentes
+        self.pixels.iter().all(|pixel| pixel[3] < 128)
+    }
+
+    pub fn crop(&self, x: u32, y: u32, width: u32, height: u32) -> Image {
+        let mut image = Image::new(width, height);
+        for j in 0..height.min(self.height.saturating_sub(y)) {
+            for i in 0..width.min(self.width.saturating_sub(x)) {
+                image.set(i, j, self.get(x + i, y + j));
+            }
+        }
+        image
+    }
+
+    pub fn downscale(&self, scale: u32) -> Image {                          // Un pixel de cada bloque de scale x scale
+        let scale = scale.max(1);
+        let mut image = Image::new(self.width / scale, self.height / scale);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                image.set(x, y, self.get(x * scale, y * scale));
+            }
+        }
+        image
+    }
+
+    pub fn decode<R: Read>(reader: R) -> Result<Image, String> {
+        let mut decoder = png::Decoder::new(reader);
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+
+        let channels = info.color_type.samples();
+        let mut image = Image::new(info.width, info.height);
+        for (pixel, data) in image.pixels.iter_mut().zip(buffer[..info.buffer_size()].chunks(channels)) {
+            *pixel = match data {
+                [gray] => [*gray, *gray, *gray, 255],
+                [gray, alpha] => [*gray, *gray, *gray, *alpha],
+                [r, g, b] => [*r, *g, *b, 255],
+                [r, g, b, a] => [*r, *g, *b, *a],
+                _ => return Err(format!("formato de color no soportado {:?}", info.color_type)),
+            };
+        }
+        Ok(image)
+    }
+
+    pub fn encode<W: Write>(&self, out: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&self.pixels.concat()).map_err(io::Error::other)
+    }
+}
+                                                                            // Sprite de una imagen, los primeros índices de color son los de colors
+pub fn import(image: &Image, colors: &[String]) -> Result<Sprite, String> {
+    if image.width == 0 || image.height == 0 || image.width > 256 || image.height > 256 {
+        return Err(format!("la imagen mide {}x{}, un sprite mide de 1x1 a 256x256", image.width, image.height));
+    }
+    let mut palette: Vec<Rgba> = Vec::new();
+    for color in colors.iter() {
+        match parse_color(color) {
+            Some(rgba) => palette.push([rgba[0], rgba[1], rgba[2], 255]),
+            None => return Err(format!("color desconocido '{}'", color)),
+        }
+    }
+    let mut names: Vec<String> = colors.to_vec();
+
+    let mut map = BitMap::with_size(image.width as usize, image.height as usize);
+    for (y, row) in map.0.iter_mut().enumerate() {
+        for (x, cell) in row.iter_mut().enumerate() {
+            let pixel = image.get(x as u32, y as u32);
+            if pixel[3] < 128 {
+                continue;
+            }
+            let opaque = [pixel[0], pixel[1], pixel[2], 255];
+            let index = match palette.iter().position(|c| *c == opaque) {
+                Some(index) => index,
+                None => {
+                    palette.push(opaque);
+                    names.push(format!("#{:02x}{:02x}{:02x}", pixel[0], pixel[1], pixel[2]));
+                    palette.len() - 1
+                },
+            };
+            if index >= 255 {
+                return Err(String::from("la imagen usa más de 255 colores"));
+            }
+            *cell = index as u8 + 1;
+        }
+    }
+    if names.is_empty() {                                                   // Un sprite siempre tiene al menos un color
+        names.push(String::from("black"));
+    }
+    Ok(Sprite::with_map(names, map))
+}
+
+pub fn cells(image: &Image, width: u32, height: u32) -> Vec<Image> {        // Celdas de una hoja de sprites, de izquierda a derecha y de arriba abajo
+    let mut list = Vec::new();
+    if width == 0 || height == 0 {
+        return list;
+    }
+    for y in (0..image.height / height).map(|j| j * height) {
+        for x in (0..image.width / width).map(|i| i * width) {
+            list.push(image.crop(x, y, width, height));
+        }
+    }
+    list
+}
+
+pub fn export(spte: &Sprite, scale: u32) -> Image {                         // Imagen de un sprite, cada celda del mapa de bits mide scale x scale pixeles
+    let scale = scale.max(1);
+    let mut image = Image::new(spte.map.width() as u32 * scale, spte.map.height() as u32 * scale);
+    draw(&mut image, spte, 0, 0, scale);
+    image
+}
+
+pub fn sheet(sprites: &[&Sprite], scale: u32) -> Image {                   // Hoja con los sprites en una fila
+    let scale = scale.max(1);
+    let width = sprites.iter().map(|spte| spte.map.width()).max().unwrap_or(0) as u32 * scale;
+    let height = sprites.iter().map(|spte| spte.map.height()).max().unwrap_or(0) as u32 * scale;
+    let mut image = Image::new(width * sprites.len() as u32, height);
+    for (i, spte) in sprites.iter().enumerate() {
+        let top = height - spte.map.height() as u32 * scale;
+        draw(&mut image, spte, i as u32 * width, top, scale);
+    }
+    image
+}
+
+fn draw(image: &mut Image, spte: &Sprite, x: u32, y: u32, scale: u32) {
+    for (i, row) in spte.map.0.iter().enumerate() {
+        for (j, pixel) in row.iter().enumerate() {
+            if *pixel == 0 {
+                continue;
+            }
+            let color = spte.colorset.get(*pixel as usize - 1).and_then(|c| parse_color(c)).unwrap_or([0, 0, 0, 255]);
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    image.set(x + j as u32 * scale + dx, y + i as u32 * scale + dy, color);
+                }
+            }
+        }
+    }
+}
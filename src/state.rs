@@ -2,11 +2,14 @@
  * Se define una enumeración con la lista de los posibles estados en los que se puede encontrar el jugador, en
  * este caso los estados Walk, Jump y Fall tienen un sub-estado que es la dirección, esta se define con el 
  * módulo direction, y es por que estando en esos estados se puede ir en diferentes direcciones.
+ *
+ * Los nombres de NAMES son las etiquetas de los estados en StateMachine_FoxHell.jff (ver fsm.rs). Cambiar
+ * sólo la dirección de Walk, Jump o Fall no es una transición, por eso el nombre no la incluye.
  */
 
 use crate::direction::Direction;
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum State {
     Stand,
     Walk(Direction),
@@ -15,3 +18,45 @@ pub enum State {
     Meteor,
     Crouch,
 }
+
+impl State {
+    pub const NAMES: [&'static str; 6] = ["Stand", "Walk", "Jump", "Fall", "Meteor", "Crouch"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            State::Stand => "Stand",
+            State::Walk(_) => "Walk",
+            State::Jump(_) => "Jump",
+            State::Fall(_) => "Fall",
+            State::Meteor => "Meteor",
+            State::Crouch => "Crouch",
+        }
+    }
+
+    pub fn from_name(name: &str, dir: Direction) -> Option<State> {         // Los estados sin dirección ignoran dir
+        match name {
+            "Stand" => Some(State::Stand),
+            "Walk" => Some(State::Walk(dir)),
+            "Jump" => Some(State::Jump(dir)),
+            "Fall" => Some(State::Fall(dir)),
+            "Meteor" => Some(State::Meteor),
+            "Crouch" => Some(State::Crouch),
+            _ => None,
+        }
+    }
+
+    pub fn dir(self) -> Direction {                                         // Direction::None en los estados sin dirección
+        match self {
+            State::Walk(dir) | State::Jump(dir) | State::Fall(dir) => dir,
+            _ => Direction::None,
+        }
+    }
+
+    pub fn has_dir(self) -> bool {
+        matches!(self, State::Walk(_) | State::Jump(_) | State::Fall(_))
+    }
+
+    pub fn with_dir(self, dir: Direction) -> State {                        // El mismo estado con otra dirección
+        State::from_name(self.name(), dir).unwrap_or(self)
+    }
+}
@@ -0,0 +1,125 @@
+/*
+ * Implementación del trait Renderer para una terminal con colores de 24 bits (ANSI truecolor). El juego se
+ * dibuja primero en un FrameBuffer a escala 1 (8x8 pixeles por celda) y después cada par de filas de pixeles
+ * se convierte en una fila de caracteres de medio bloque '▀', usando el color de la fila superior como color
+ * del texto y el de la inferior como color de fondo. Así un mapa de 20x10 ocupa 160 columnas y 40 filas.
+ *
+ * Los textos de draw_text se escriben encima del mapa en la celda de caracteres correspondiente, y el status
+ * de la máquina de estados se muestra en una línea debajo del mapa, ya que en la terminal no hay un segundo
+ * canvas: los nombres de los estados del diagrama con el actual resaltado y después las transiciones más
+ * recientes del historial, hasta donde alcance el ancho del mapa.
+ *
+ * Este módulo sólo genera las cadenas con las secuencias de escape; leer el teclado y escribir en la
+ * terminal le corresponde al binario fox_hell_term.
+ */
+
+use std::fmt::Write;
+
+use crate::color::{parse_color, Rgba};
+use crate::framebuffer::FrameBuffer;
+use crate::graph::StateGraph;
+use crate::renderer::Renderer;
+use crate::sprite::Sprite;
+
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    frame: FrameBuffer,
+    graph: StateGraph,
+}
+
+impl Terminal {
+    pub fn new(width: u32, height: u32) -> Terminal {                      // width y height en celdas del mapa
+        Terminal {
+            frame: FrameBuffer::new(width, height, 1),
+            graph: StateGraph::default(),
+        }
+    }
+
+    pub fn columns(&self) -> u32 {                                          // Columnas de la terminal que ocupa el mapa
+        self.frame.pixel_width()
+    }
+
+    pub fn rows(&self) -> u32 {                                             // Filas de la terminal que ocupa el mapa (sin la línea de status)
+        self.frame.pixel_height() / 2
+    }
+
+    pub fn render(&self) -> String {                                        // Cuadro completo con secuencias de escape, empezando en la esquina superior izquierda
+        let mut overlay: Vec<Option<(char, Rgba)>> = vec![None; (self.columns() * self.rows()) as usize];
+        for text in self.frame.texts.iter() {
+            let col = (text.x * 8.0) as i64;
+            let row = (text.y * 4.0) as i64;
+            if row < 0 || row >= self.rows() as i64 {
+                continue;
+            }
+            let color = parse_color(&text.color).unwrap_or([255, 255, 255, 255]);
+            for (i, c) in text.text.chars().enumerate() {
+                let x = col + i as i64;
+                if x >= 0 && x < self.columns() as i64 {
+                    overlay[(row * self.columns() as i64 + x) as usize] = Some((c, color));
+                }
+            }
+        }
+
+        let mut out = String::from("\x1b[H");
+        for row in 0..self.rows() {
+            let mut last: Option<(Rgba, Rgba)> = None;                      // Sólo se cambian los colores cuando son distintos a los del caracter anterior
+            for col in 0..self.columns() {
+                let top = self.frame.get_pixel(col, row * 2);
+                let bottom = self.frame.get_pixel(col, row * 2 + 1);
+                let (c, fg, bg) = match overlay[(row * self.columns() + col) as usize] {
+                    Some((c, color)) => (c, color, top),
+                    None => ('\u{2580}', top, bottom),
+                };
+                if last != Some((fg, bg)) {
+                    let _ = write!(out, "\x1b[38;2;{};{};{};48;2;{};{};{}m", fg[0], fg[1], fg[2], bg[0], bg[1], bg[2]);
+                    last = Some((fg, bg));
+                }
+                out.push(c);
+            }
+            out.push_str("\x1b[0m\r\n");
+        }
+
+        out.push_str(&self.render_status());
+        out.push_str("\x1b[0m\x1b[K");
+        out
+    }
+
+    fn render_status(&self) -> String {                                     // Línea de status con el estado actual resaltado y el historial
+        let mut out = String::new();
+        let mut used = 0;                                                   // Columnas ocupadas, sin contar las secuencias de escape
+        for (i, node) in self.graph.nodes.iter().enumerate() {
+            used += node.label.chars().count() + 3;
+            if used > self.columns() as usize {
+                return out;
+            }
+            let color = if self.graph.active == Some(i) { "30;42" } else { "37;44" };
+            let _ = write!(out, "\x1b[{}m {} \x1b[0m ", color, node.label);
+        }
+        for text in self.graph.history.iter().rev() {                       // Primero la más reciente
+            used += text.chars().count() + 3;
+            if used > self.columns() as usize {
+                break;
+            }
+            let _ = write!(out, " | {}", text);
+        }
+        out
+    }
+}
+
+impl Renderer for Terminal {
+    fn clear(&mut self, bg: &str) {
+        self.frame.clear(bg);
+    }
+
+    fn draw_sprite(&mut self, x: f64, y: f64, spte: &Sprite) {
+        self.frame.draw_sprite(x, y, spte);
+    }
+
+    fn draw_text(&mut self, x: f64, y: f64, text: &str, color: &str) {
+        self.frame.draw_text(x, y, text, color);
+    }
+
+    fn draw_graph(&mut self, graph: &StateGraph) {
+        self.graph = graph.clone();
+    }
+}
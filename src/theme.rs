@@ -0,0 +1,131 @@
+/*
+ * Temas de colores. Un tema cambia unos colores por otros en todos los sprites y en los fondos de los mapas
+ * (por ejemplo un Ice Hell en tonos azules, un modo nocturno o un tema seguro para daltónicos), sin tocar los
+ * archivos de los mapas ni de los sprites. Los temas se declaran en themes.txt, que se carga como los demás
+ * archivos del juego (ver assets.rs), y siguen el estilo de levels.txt: una versión en la primera línea y
+ * una sección ThemeStart/ThemeEnd por tema con su nombre y una línea color:reemplazo por cada color:
+ *   FoxHellThemes 1
+ *   ThemeStart
+ *   Name:Ice Hell
+ *   #2f0000:#001a2f
+ *   red:#00b0f0
+ *   ThemeEnd
+ *
+ * Los colores se comparan ya convertidos (red y #ff0000 son el mismo color), y los que no aparecen en el tema
+ * se quedan igual. El primer tema siempre es el Clásico, que no cambia ningún color, y después vienen los de
+ * themes.txt en orden. Character aplica el tema al cargar los sprites y los mapas, y al cambiar de tema sólo
+ * cambia los colores, así que las animaciones y las celdas del mapa no se reinician.
+ */
+
+use crate::assets::Assets;
+use crate::color::{parse_color, Rgba};
+use crate::gamemap::GameMap;
+use crate::sprite::Sprite;
+
+pub const THEMES_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    pub name: String,
+    pub colors: Vec<(Rgba, String)>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+impl Theme {
+    pub fn classic() -> Theme {                                             // Tema que deja los colores de los archivos
+        Theme {
+            name: String::from("Clásico"),
+            colors: Vec::new(),
+        }
+    }
+
+    pub fn color(&self, color: &str) -> String {                            // El reemplazo de un color, o el mismo si el tema no lo cambia
+        let rgba = parse_color(color);
+        match self.colors.iter().find(|(from, _)| Some(*from) == rgba) {
+            Some((_, to)) => to.clone(),
+            None => color.to_string(),
+        }
+    }
+
+    pub fn apply_sprite(&self, spte: &mut Sprite, original: &Sprite) {      // Colores de original con el tema, sin cambiar el mapa de bits de spte
+        spte.colorset = original.colorset.iter().map(|color| self.color(color)).collect();
+    }
+
+    pub fn apply_map(&self, map: &mut GameMap, original: &GameMap) {        // Igual que apply_sprite para los sprites y los fondos de un mapa
+        for (spte, orig) in map.sprites.iter_mut().zip(original.sprites.iter()) {
+            self.apply_sprite(spte, orig);
+        }
+        map.bg = self.color(&original.bg);
+        map.bg_temp = self.color(&original.bg_temp);
+    }
+
+    pub fn from_assets(assets: &Assets) -> Vec<Theme> {                     // El tema Clásico y los de themes.txt (sólo el Clásico si el archivo tiene errores)
+        let mut themes = vec![Theme::classic()];
+        if let Ok(list) = Theme::parse(&assets.text("themes.txt")) {
+            themes.extend(list);
+        }
+        themes
+    }
+                                                                            // Temas de un archivo, el error indica la línea (0 si es de todo el archivo) y la razón
+    pub fn parse(text: &str) -> Result<Vec<Theme>, (usize, String)> {
+        let mut lines = text.lines().enumerate();
+
+        match lines.next() {
+            Some((_, header)) if header.trim() == format!("FoxHellThemes {}", THEMES_VERSION) => {},
+            Some((_, header)) => return Err((1, format!("versión de temas no soportada '{}'", header))),
+            None => return Err((0, String::from("lista de temas vacía"))),
+        }
+
+        let mut themes: Vec<Theme> = Vec::new();
+        let mut current: Option<Theme> = None;
+        for (i, line) in lines {
+            let line = line.trim();
+            let fail = |reason: &str| (i + 1, format!("{} '{}'", reason, line));
+
+            if line.is_empty() {
+                continue;
+            }
+            match (line, current.take()) {
+                ("ThemeStart", None) => current = Some(Theme { name: String::new(), colors: Vec::new() }),
+                ("ThemeEnd", Some(theme)) if theme.name.is_empty() => return Err(fail("tema sin nombre")),
+                ("ThemeEnd", Some(theme)) => {
+                    if themes.iter().any(|t| t.name == theme.name) || theme.name == Theme::classic().name {
+                        return Err(fail("tema repetido"));
+                    }
+                    themes.push(theme);
+                },
+                (_, None) => return Err(fail("línea fuera de un tema")),
+                (_, Some(mut theme)) => {
+                    let mut parts = line.splitn(2, ':');
+                    match (parts.next(), parts.next().map(|v| v.trim())) {
+                        (Some("Name"), Some(name)) => theme.name = name.to_string(),
+                        (Some(from), Some(to)) => {
+                            let rgba = match parse_color(from) {
+                                Some(rgba) => rgba,
+                                None => return Err(fail("color desconocido")),
+                            };
+                            if parse_color(to).is_none() {
+                                return Err(fail("color de reemplazo desconocido"));
+                            }
+                            if theme.colors.iter().any(|(c, _)| *c == rgba) {
+                                return Err(fail("color repetido"));
+                            }
+                            theme.colors.push((rgba, to.to_string()));
+                        },
+                        _ => return Err(fail("se esperaba color:reemplazo")),
+                    }
+                    current = Some(theme);
+                },
+            }
+        }
+        if current.is_some() {
+            return Err((0, String::from("falta ThemeEnd al final del archivo")));
+        }
+        Ok(themes)
+    }
+}
@@ -0,0 +1,130 @@
+/*
+ * Propiedades de las celdas de un mapa. Cada sprite de la sección SpriteStart de un archivo de mapa puede
+ * declarar, después del mapa de bits, una lista de propiedades separadas por comas:
+ *   BrickFloor:#d9d9d9-#757171:0-16750848-26367:solid
+ *   MedKit:black-white-red:0-35604928810450944-9122531142237299736:pickup=life
+ *   Saw:#808080-#d0cece-black:0-4340410370284600380-15153436594310730315:anim=invert/2
+ *
+ * Las propiedades que se reconocen son:
+ *   solid               el zorro puede pararse encima de la celda
+ *   hazard              el zorro pierde una vida al entrar en la celda
+ *   pickup=life         al agacharse sobre la celda se recupera una vida (si no se tiene el máximo)
+ *   pickup=score/N      al agacharse sobre la celda se suman N puntos
+ *   anim=invert/N       el sprite se voltea horizontalmente cada N iteraciones
+ *   anim=rotate/N       el sprite se gira 90 grados cada N iteraciones
+ *   hud=heart           el sprite con el que el HUD muestra cada vida
+ *   hud=digit/N         el sprite con el que el HUD y las pantallas muestran el dígito N (0-9)
+ *   decor=sky           decoración que el generador de arenas pone en el cielo (ver arena.rs)
+ *   decor=ground        decoración que el generador de arenas pone en el suelo
+ * Los objetos que se recogen desaparecen del mapa. El HUD busca sus sprites por estas propiedades
+ * (GameMap::hud_tile), así que el orden de los sprites en el archivo no importa.
+ */
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Pickup {
+    Life,
+    Score(u16),
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum AnimationKind {
+    Invert,
+    Rotate,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Animation {
+    pub kind: AnimationKind,
+    pub period: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum HudRole {
+    Heart,
+    Digit(u8),
+}
+
+impl fmt::Display for HudRole {                                             // Igual que en el archivo, sin el hud=
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HudRole::Heart => write!(f, "heart"),
+            HudRole::Digit(digit) => write!(f, "digit/{}", digit),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Decor {
+    Sky,
+    Ground,
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+pub struct TileProperties {
+    pub solid: bool,
+    pub hazard: bool,
+    pub pickup: Option<Pickup>,
+    pub animation: Option<Animation>,
+    pub hud: Option<HudRole>,
+    pub decor: Option<Decor>,
+}
+
+impl TileProperties {
+    pub fn from_line(line: &str) -> TileProperties {                        // Propiedades de una línea de sprite, sin propiedades si no tiene cuarto campo
+        match line.split(':').nth(3) {
+            Some(text) => TileProperties::from_text(text),
+            None => TileProperties::default(),
+        }
+    }
+
+    pub fn from_text(text: &str) -> TileProperties {
+        let mut props = TileProperties::default();
+        for prop in text.split(',').map(|p| p.trim()) {
+            let mut parts = prop.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some("solid"), None) => props.solid = true,
+                (Some("hazard"), None) => props.hazard = true,
+                (Some("pickup"), Some(value)) => props.pickup = parse_pickup(value),
+                (Some("anim"), Some(value)) => props.animation = parse_animation(value),
+                (Some("hud"), Some(value)) => props.hud = parse_hud(value),
+                (Some("decor"), Some("sky")) => props.decor = Some(Decor::Sky),
+                (Some("decor"), Some("ground")) => props.decor = Some(Decor::Ground),
+                _ => (),
+            }
+        }
+        props
+    }
+}
+
+fn parse_pickup(text: &str) -> Option<Pickup> {
+    let mut parts = text.splitn(2, '/');
+    match (parts.next(), parts.next()) {
+        (Some("life"), None) => Some(Pickup::Life),
+        (Some("score"), Some(points)) => points.parse().ok().map(Pickup::Score),
+        _ => None,
+    }
+}
+
+fn parse_hud(text: &str) -> Option<HudRole> {
+    let mut parts = text.splitn(2, '/');
+    match (parts.next(), parts.next()) {
+        (Some("heart"), None) => Some(HudRole::Heart),
+        (Some("digit"), Some(digit)) => digit.parse().ok().filter(|d| *d < 10).map(HudRole::Digit),
+        _ => None,
+    }
+}
+
+fn parse_animation(text: &str) -> Option<Animation> {
+    let mut parts = text.splitn(2, '/');
+    let kind = match parts.next() {
+        Some("invert") => AnimationKind::Invert,
+        Some("rotate") => AnimationKind::Rotate,
+        _ => return None,
+    };
+    match parts.next().and_then(|p| p.parse::<usize>().ok()) {
+        Some(period) if period > 0 => Some(Animation { kind, period }),
+        _ => None,
+    }
+}
@@ -0,0 +1,449 @@
+/*
+ * Validación de los archivos de mapas, sprites y niveles. GameMap::from_text sólo rechaza filas de distinto
+ * ancho y en lo demás supone que el archivo es correcto (los incluidos en el binario lo son), así que otro
+ * error en un mapa hecho a mano termina en un mapa distinto al esperado; Sprite::from_line revisa su línea
+ * con check_sprite_line. Estas funciones revisan el archivo
+ * completo y regresan todos los errores encontrados, cada uno con el archivo, la línea y la columna (empezando
+ * en 1) y la razón del error, con el formato archivo:línea:columna: razón.
+ *
+ * En los mapas se revisa:
+ *   - que cada línea pertenezca a una sección conocida (MapStart, SpriteStart, BackgroundStart, BackTempStart
+ *     u OptionsStart, cada una con su fin; una línea vacía también termina la sección)
+ *   - las dimensiones: al menos 3 filas y 3 columnas, todas las filas del mismo largo y el Viewport dentro
+ *     del ancho del mapa
+ *   - que cada celda sea 0-9 o A-Z y que tenga un sprite definido
+ *   - cada sprite: Nombre:colores:mapa de bits[:propiedades], con 1 a 255 colores de CSS o paletas
+ *     declaradas antes, un mapa de bits (tres u64 separados por guiones o ANCHOxALTO[xBITS].DATOS) cuyos
+ *     índices de color estén definidos y propiedades que tile.rs reconozca
+ *   - cada paleta: @Nombre:colores, con un nombre que no se repita y colores de CSS
+ *   - los colores de fondo y las opciones
+ *   - que estén los sprites del HUD que el juego usa con ese mapa (required_hud): los dígitos en mapEnd.txt,
+ *     mapVictory.txt y mapLevel.txt, ninguno en mapStart.txt y mapPause.txt, y el corazón y los dígitos en
+ *     los demás, que son mapas de niveles
+ * Los archivos de sprites sólo tienen paletas y líneas de sprite con nombres distintos; fox_sprites.txt además
+ * debe tener todos los sprites del zorro y los proyectiles que usa el juego. Las listas
+ * de niveles se revisan con Campaign::parse_with usando los mapas que se indiquen (los incluidos en el
+ * binario con check_file). Los temas (themes.txt) se revisan con Theme::parse.
+ */
+
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+use crate::assets::{self, Assets};
+use crate::bitmap::BitMap;
+use crate::campaign::Campaign;
+use crate::color::parse_color;
+use crate::gamemap::MAX_SPRITES;
+use crate::sprite::{Palettes, Sprite};
+use crate::theme::Theme;
+use crate::tile::{HudRole, TileProperties};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.reason)
+    }
+}
+
+struct Errors<'a> {                                                         // Acumula los errores de un archivo
+    file: &'a str,
+    list: Vec<FileError>,
+}
+
+impl<'a> Errors<'a> {
+    fn push(&mut self, line: usize, column: usize, reason: String) {
+        self.list.push(FileError {
+            file: self.file.to_string(),
+            line,
+            column,
+            reason,
+        });
+    }
+}
+
+pub fn check_file(file: &str, text: &str) -> Vec<FileError> {              // Revisa un archivo según su contenido
+    check_file_with(file, text, &Assets::embedded())
+}
+                                                                            // Igual que check_file, los niveles pueden usar los mapas de assets
+pub fn check_file_with(file: &str, text: &str, assets: &Assets) -> Vec<FileError> {
+    if text.starts_with("FoxHellLevels") {
+        check_levels_with(file, text, assets)
+    } else if text.starts_with("FoxHellThemes") {
+        check_themes(file, text)
+    } else if text.lines().any(|line| line.trim() == "MapStart") {
+        check_map(file, text)
+    } else {
+        check_sprites(file, text)
+    }
+}
+
+fn file_name(file: &str) -> &str {                                          // Nombre del archivo sin el directorio
+    Path::new(file).file_name().and_then(|name| name.to_str()).unwrap_or(file)
+}
+
+pub fn level_hud() -> Vec<HudRole> {                                        // Sprites del HUD de un nivel: las vidas, el puntaje y el tiempo
+    let mut roles = vec![HudRole::Heart];
+    roles.extend((0..10).map(HudRole::Digit));
+    roles
+}
+
+pub fn required_hud(file: &str) -> Vec<HudRole> {                           // Sprites del HUD que el juego usa con un mapa, según su nombre
+    match file_name(file) {
+        "mapStart.txt" | "mapPause.txt" => Vec::new(),
+        "mapEnd.txt" | "mapVictory.txt" | "mapLevel.txt" => (0..10).map(HudRole::Digit).collect(),
+        _ => level_hud(),
+    }
+}
+
+pub fn check_map(file: &str, text: &str) -> Vec<FileError> {
+    check_map_with(file, text, &required_hud(file))
+}
+                                                                            // Igual que check_map, con los sprites del HUD que debe tener el mapa
+pub fn check_map_with(file: &str, text: &str, required: &[HudRole]) -> Vec<FileError> {
+    let mut errors = Errors { file, list: Vec::new() };
+    let mut section: Option<&str> = None;
+    let mut map_line = None;
+    let mut sprite_line = None;
+    let mut roles = Vec::new();
+    let mut rows: Vec<(usize, &str)> = Vec::new();
+    let mut sprites = 0;
+    let mut palettes = Palettes::new();
+    let mut viewport: Option<(usize, u32)> = None;
+
+    for (i, line) in text.lines().enumerate() {
+        let n = i + 1;
+        if line.is_empty() {
+            section = None;
+            continue;
+        }
+        match line {
+            "MapStart" | "SpriteStart" | "BackgroundStart" | "BackTempStart" | "OptionsStart" => {
+                if line == "MapStart" {
+                    map_line = Some(n);
+                } else if line == "SpriteStart" {
+                    sprite_line = Some(n);
+                }
+                section = Some(line.trim_end_matches("Start"));
+                continue;
+            },
+            "MapEnd" | "SpriteEnd" | "BackgroundEnd" | "BackTempEnd" | "OptionsEnd" => {
+                let name = line.trim_end_matches("End");
+                match section {
+                    Some(open) if open != name => errors.push(n, 1, format!("se esperaba {}End", open)),
+                    _ => (),
+                }
+                section = None;
+                continue;
+            },
+            _ => (),
+        }
+
+        match section {
+            Some("Map") => {
+                for (j, c) in line.chars().enumerate() {
+                    if !c.is_ascii_digit() && !c.is_ascii_uppercase() {
+                        errors.push(n, j + 1, format!("carácter de celda desconocido '{}'", c));
+                    }
+                }
+                rows.push((n, line));
+            },
+            Some("Sprite") if line.starts_with('@') => {
+                if let Err((column, reason)) = check_palette_line(line, &mut palettes) {
+                    errors.push(n, column, reason);
+                }
+            },
+            Some("Sprite") => {
+                sprites += 1;
+                match check_sprite_line_with(line, &palettes) {
+                    Ok(()) => roles.extend(TileProperties::from_line(line).hud),
+                    Err((column, reason)) => errors.push(n, column, reason),
+                }
+            },
+            Some("Background") | Some("BackTemp") => {
+                if parse_color(line).is_none() {
+                    errors.push(n, 1, format!("color desconocido '{}'", line));
+                }
+            },
+            Some(_) => {
+                let mut parts = line.splitn(2, ':');
+                match (parts.next(), parts.next().map(|v| v.trim())) {
+                    (Some("Wrap"), Some("true")) | (Some("Wrap"), Some("false")) => (),
+                    (Some("Viewport"), Some(value)) => match value.parse::<u32>() {
+                        Ok(width) if width > 0 => viewport = Some((n, width)),
+                        _ => errors.push(n, 10, format!("ancho de ventana inválido '{}'", value)),
+                    },
+                    (Some("Wrap"), Some(value)) => errors.push(n, 6, format!("se esperaba true o false '{}'", value)),
+                    _ => errors.push(n, 1, format!("opción desconocida '{}'", line)),
+                }
+            },
+            None if line.starts_with(|c: char| c.is_ascii_uppercase()) && line.ends_with("Start") => {
+                errors.push(n, 1, format!("sección desconocida '{}'", line));
+            },
+            None => errors.push(n, 1, format!("línea fuera de una sección '{}'", line)),
+        }
+    }
+
+    if sprites > MAX_SPRITES {
+        errors.push(1, 1, format!("{} sprites, las celdas sólo pueden usar {} (1-9 y A-Z)", sprites, MAX_SPRITES));
+    }
+    let width = rows.first().map(|(_, row)| row.chars().count()).unwrap_or(0);
+    match map_line {
+        None => errors.push(1, 1, String::from("falta la sección MapStart")),
+        Some(n) if rows.len() < 3 || width < 3 => {
+            errors.push(n, 1, format!("el mapa debe tener al menos 3x3 celdas, tiene {}x{}", width, rows.len()));
+        },
+        Some(_) => (),
+    }
+    for (n, row) in rows.iter() {
+        let len = row.chars().count();
+        if len != width {
+            errors.push(*n, len.min(width) + 1, format!("fila de {} celdas, se esperaban {}", len, width));
+        }
+        for (j, c) in row.chars().enumerate() {
+            let tile = match c {
+                '1'..='9' => c as usize - '0' as usize,
+                'A'..='Z' => c as usize - 'A' as usize + 10,
+                _ => continue,
+            };
+            if tile > sprites {
+                errors.push(*n, j + 1, format!("la celda '{}' no tiene sprite, sólo hay {}", c, sprites));
+            }
+        }
+    }
+    if let Some((n, view)) = viewport {
+        if view as usize > width {
+            errors.push(n, 10, format!("la ventana de {} columnas es más ancha que el mapa ({})", view, width));
+        }
+    }
+    for role in required.iter().filter(|role| !roles.contains(role)) {
+        errors.push(sprite_line.unwrap_or(1), 1, format!("falta el sprite con la propiedad hud={}", role));
+    }
+
+    errors.list.sort_by_key(|err| (err.line, err.column));
+    errors.list
+}
+
+pub fn check_sprites(file: &str, text: &str) -> Vec<FileError> {
+    let mut errors = Errors { file, list: Vec::new() };
+    let mut names = HashSet::new();
+    let mut palettes = Palettes::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('@') {
+            if let Err((column, reason)) = check_palette_line(line, &mut palettes) {
+                errors.push(i + 1, column, reason);
+            }
+        } else if let Err((column, reason)) = check_sprite_line_with(line, &palettes) {
+            errors.push(i + 1, column, reason);
+        } else if !names.insert(line.split(':').next().unwrap_or("")) {
+            errors.push(i + 1, 1, String::from("nombre de sprite repetido"));
+        }
+    }
+    if file_name(file) == "fox_sprites.txt" {                               // Los sprites que el juego busca por nombre
+        let required = assets::embedded("fox_sprites.txt").unwrap_or("");
+        for line in required.lines().filter(|line| !line.starts_with('@')) {
+            let sprite = line.split(':').next().unwrap_or("");
+            if !names.contains(sprite) {
+                errors.push(1, 1, format!("falta el sprite {}", sprite));
+            }
+        }
+    }
+    errors.list
+}
+
+pub fn check_levels(file: &str, text: &str) -> Vec<FileError> {
+    check_levels_with(file, text, &Assets::embedded())
+}
+
+pub fn check_themes(file: &str, text: &str) -> Vec<FileError> {
+    let mut errors = Errors { file, list: Vec::new() };
+    if let Err((line, reason)) = Theme::parse(text) {
+        errors.push(line.max(1), 1, reason);
+    }
+    errors.list
+}
+
+pub fn check_levels_with(file: &str, text: &str, assets: &Assets) -> Vec<FileError> {
+    let mut errors = Errors { file, list: Vec::new() };
+    if let Err((line, reason)) = Campaign::parse_with(text, assets) {
+        errors.push(line.max(1), 1, reason);
+    }
+    errors.list
+}
+                                                                            // Revisa una línea Nombre:colores:mapa de bits[:propiedades], regresa la columna y la razón del error
+pub fn check_sprite_line(line: &str) -> Result<(), (usize, String)> {
+    check_sprite_line_with(line, &Palettes::new())
+}
+                                                                            // Igual que check_sprite_line, los colores pueden usar las paletas ya declaradas
+pub fn check_sprite_line_with(line: &str, palettes: &Palettes) -> Result<(), (usize, String)> {
+    let fields: Vec<&str> = line.split(':').collect();
+    let column = |field: usize| fields.iter().take(field).map(|f| f.len() + 1).sum::<usize>() + 1;
+
+    if fields[0].is_empty() {
+        return Err((1, String::from("sprite sin nombre")));
+    }
+    if fields.len() < 3 || fields.len() > 4 {
+        return Err((1, format!("se esperaba Nombre:colores:mapa de bits[:propiedades], hay {} campos", fields.len())));
+    }
+
+    let mut colors = 0;
+    let mut offset = column(1);
+    for color in fields[1].split('-') {
+        match color.strip_prefix('@') {
+            Some(name) => match palettes.get(name) {
+                Some(palette) => colors += palette.len(),
+                None => return Err((offset, format!("paleta desconocida '{}'", name))),
+            },
+            None if parse_color(color).is_none() => return Err((offset, format!("color desconocido '{}'", color))),
+            None => colors += 1,
+        }
+        offset += color.len() + 1;
+    }
+    if colors > 255 {
+        return Err((column(1), format!("{} colores, un sprite tiene como máximo 255", colors)));
+    }
+
+    let bitmap = BitMap::from_text(fields[2]).map_err(|(offset, reason)| (column(2) + offset, reason))?;
+    if let Some(max) = bitmap.0.iter().flatten().max() {
+        if *max as usize > colors {
+            return Err((column(2), format!("el mapa de bits usa el color {} y sólo hay {}", max, colors)));
+        }
+    }
+
+    if let Some(props) = fields.get(3) {
+        let mut offset = column(3);
+        for prop in props.split(',') {
+            if TileProperties::from_text(prop) == TileProperties::default() {
+                return Err((offset, format!("propiedad desconocida '{}'", prop)));
+            }
+            offset += prop.len() + 1;
+        }
+    }
+    Ok(())
+}
+                                                                            // Revisa una línea @Nombre:colores y agrega la paleta a palettes
+pub fn check_palette_line(line: &str, palettes: &mut Palettes) -> Result<(), (usize, String)> {
+    let (name, colors) = match Sprite::parse_palette(line) {
+        Some(palette) => palette,
+        None => return Err((1, String::from("se esperaba @Nombre:colores"))),
+    };
+    if name.is_empty() {
+        return Err((2, String::from("paleta sin nombre")));
+    }
+    if palettes.contains_key(&name) {
+        return Err((2, format!("paleta repetida '{}'", name)));
+    }
+    if colors.len() > 255 {
+        return Err((name.len() + 3, format!("{} colores, una paleta tiene como máximo 255", colors.len())));
+    }
+    let mut offset = name.len() + 3;
+    for color in colors.iter() {
+        if parse_color(color).is_none() {
+            return Err((offset, format!("color desconocido '{}'", color)));
+        }
+        offset += color.len() + 1;
+    }
+    palettes.insert(name, colors);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "MapStart\n111\n101\n111\nMapEnd\nSpriteStart\nWall:red:0-0-0\nSpriteEnd\n";
+
+    fn reasons(errors: &[FileError]) -> Vec<(usize, usize, &str)> {
+        errors.iter().map(|err| (err.line, err.column, err.reason.as_str())).collect()
+    }
+
+    #[test]
+    fn embedded_files_have_no_errors() {
+        for file in assets::FILES.iter() {
+            let text = assets::embedded(file).unwrap();
+            assert_eq!(check_file(file, text), Vec::new(), "{}", file);
+        }
+    }
+
+    #[test]
+    fn reports_file_line_and_column() {
+        let errors = check_map_with("map.txt", &MAP.replace("101", "1x1"), &[]);
+        assert_eq!(errors.iter().map(|err| err.to_string()).collect::<Vec<_>>(), ["map.txt:3:2: carácter de celda desconocido 'x'"]);
+    }
+
+    #[test]
+    fn reports_bad_map_dimensions() {
+        let errors = check_map_with("map.txt", &MAP.replace("101", "1011"), &[]);
+        assert_eq!(reasons(&errors), [(3, 4, "fila de 4 celdas, se esperaban 3")]);
+
+        let errors = check_map_with("map.txt", &MAP.replace("111\n101\n", ""), &[]);
+        assert_eq!(reasons(&errors), [(1, 1, "el mapa debe tener al menos 3x3 celdas, tiene 3x1")]);
+
+        let errors = check_map_with("map.txt", &MAP.replace("SpriteStart", "OptionsStart\nViewport:4\nOptionsEnd\nSpriteStart"), &[]);
+        assert_eq!(reasons(&errors), [(7, 10, "la ventana de 4 columnas es más ancha que el mapa (3)")]);
+    }
+
+    #[test]
+    fn reports_tiles_without_sprite() {
+        let errors = check_map_with("map.txt", &MAP.replace("101", "1B1"), &[]);
+        assert_eq!(reasons(&errors), [(3, 2, "la celda 'B' no tiene sprite, sólo hay 1")]);
+    }
+
+    #[test]
+    fn reports_unknown_sections_and_lines() {
+        let errors = check_map_with("map.txt", &format!("{}\nTilesStart\nFoo\n", MAP), &[]);
+        assert_eq!(reasons(&errors), [(10, 1, "sección desconocida 'TilesStart'"), (11, 1, "línea fuera de una sección 'Foo'")]);
+
+        let errors = check_map_with("map.txt", "SpriteStart\nWall:red:0-0-0\nSpriteEnd\n", &[]);
+        assert_eq!(reasons(&errors), [(1, 1, "falta la sección MapStart")]);
+    }
+
+    #[test]
+    fn reports_missing_hud_sprites() {
+        let errors = check_map("map0.txt", MAP);
+        assert_eq!(errors.len(), 11);
+        assert_eq!(reasons(&errors[..1]), [(6, 1, "falta el sprite con la propiedad hud=heart")]);
+        assert_eq!(check_map("mapStart.txt", MAP), Vec::new());
+    }
+
+    #[test]
+    fn reports_sprite_line_errors() {
+        assert_eq!(check_sprite_line("Wall:red:0-0-0"), Ok(()));
+        assert_eq!(check_sprite_line(":red:0-0-0"), Err((1, String::from("sprite sin nombre"))));
+        assert_eq!(check_sprite_line("Wall:red-nocolor:0-0-0"), Err((10, String::from("color desconocido 'nocolor'"))));
+        assert_eq!(check_sprite_line("Wall:red").map_err(|(column, _)| column), Err(1));
+        assert_eq!(check_sprite_line("Wall:red:0-0").map_err(|(column, _)| column), Err(10));
+        assert_eq!(check_sprite_line("Wall:red:0-x-0").map_err(|(column, _)| column), Err(12));
+        assert_eq!(check_sprite_line("Wall:red:1-1-1").map_err(|(column, _)| column), Err(10));
+        assert_eq!(check_sprite_line("Wall:red:0-0-0:shiny").map_err(|(column, _)| column), Err(16));
+        assert_eq!(check_sprite_line("Wall:@Fox:0-0-0").map_err(|(column, _)| column), Err(6));
+    }
+
+    #[test]
+    fn reports_sprite_file_errors() {
+        let errors = check_sprites("sprites.txt", "Wall:red:0-0-0\n\n@Fire:red-orange\nWall:@Fire:0-0-0\n");
+        assert_eq!(reasons(&errors), [(4, 1, "nombre de sprite repetido")]);
+
+        let errors = check_sprites("fox_sprites.txt", "Wall:red:0-0-0\n");
+        assert!(!errors.is_empty());
+        assert!(errors.iter().all(|err| err.reason.starts_with("falta el sprite ")));
+    }
+
+    #[test]
+    fn reports_unknown_level_maps() {
+        let text = assets::embedded("levels.txt").unwrap().replacen("Map:map0.txt", "Map:nomap.txt", 1);
+        let errors = check_file("levels.txt", &text);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].reason.contains("nomap.txt"), "{}", errors[0]);
+    }
+}
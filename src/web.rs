@@ -0,0 +1,210 @@
+/*
+ * Eventos del teclado y loop del juego en el navegador. La semilla del generador de números aleatorios con
+ * el que se disparan los proyectiles se obtiene de Math.random() al iniciar la página.
+ *
+ * Con la tecla 's' se guarda una fotografía de la partida en el localStorage del navegador y con 'l' se
+ * reanuda la última partida guardada. En la pantalla de inicio la tecla 't' cambia el tema de colores (ver
+ * theme.rs).
+ *
+ * Antes de crear al jugador se descargan del servidor (carpeta files/, en static/files) los sprites, mapas y
+ * niveles con fetch, así se puede cambiar un mapa y sólo recargar la página. Si un archivo no se puede
+ * descargar o tiene errores se usa el incluido en el binario, y los errores se muestran en la consola.
+ * Con la tecla 'r' se vuelven a descargar y se cambian los mapas y sprites sin reiniciar la partida.
+ *
+ * El juego se actualiza cada 45 ms con set_timeout, pero se dibuja en cada cuadro del navegador con
+ * requestAnimationFrame: entre un update y otro el zorro y los proyectiles se dibujan entre su posición
+ * anterior y la actual (Character::draw_interpolated), así se mueven suavemente aunque el juego avance por
+ * pasos.
+ */
+
+use crate::canvas::Canvas;                                                  // Importación de la clase Canvas
+use fox_hell_core::assets::{self, Assets};                                  // Importación de los archivos del juego
+use fox_hell_core::campaign::Campaign;                                      // Importación de la lista de niveles
+use fox_hell_core::character::Character;                                    // Importación de la clase Character
+use fox_hell_core::input::Input;                                            // Importación de la enumeración Input
+use fox_hell_core::random::XorShift;                                        // Importación del generador de números aleatorios
+use fox_hell_core::snapshot::Snapshot;                                      // Importación de la fotografía del juego
+use fox_hell_core::validate::FileError;                                     // Importación de los errores de los archivos
+
+use stdweb::Once;                                                           // Importación de los callbacks que JavaScript llama una sola vez
+use stdweb::traits::*;                                                      // Importación de reglas básicas de la librería estándar web para WASM
+use stdweb::unstable::TryInto;                                              // Importación de la conversión de valores de JavaScript
+use stdweb::web::{event::{KeyDownEvent, KeyUpEvent}, IEventTarget};         // Importación de los eventos de JavaScript
+
+use std::cell::{Cell, RefCell};                                             // Importación de métodos de las clases Cell y RefCell
+use std::rc::Rc;                                                            // Importación de la clase Rc (RefCell)
+
+fn js_seed() -> u64 {                                                       // Semilla aleatoria obtenida del navegador
+    let seed: f64 = js!(return Math.floor(Math.random() * 4294967296)).try_into().unwrap();
+    seed as u64
+}
+
+
+const SNAPSHOT_KEY: &str = "fox_hell_snapshot";                            // Llave del localStorage donde se guarda la partida
+
+fn save_snapshot(character: &Character) {                                   // Guarda la fotografía de la partida en el localStorage
+    let _ = stdweb::web::window().local_storage().insert(SNAPSHOT_KEY, &character.snapshot().to_text());
+}
+
+fn load_snapshot(character: &mut Character) {                               // Reemplaza al jugador con la partida guardada, si existe y es válida
+    let text = match stdweb::web::window().local_storage().get(SNAPSHOT_KEY) {
+        Some(text) => text,
+        None => return,
+    };
+    let assets = character.assets().clone();
+    if let Ok(restored) = Snapshot::from_text(&text).and_then(|s| Character::from_snapshot_with(&s, assets)) {
+        *character = restored;
+    }
+}
+
+const ASSETS_URL: &str = "files/";                                          // Carpeta del servidor de donde se descargan los archivos del juego
+
+fn fetch_text<F: FnOnce(Option<String>) + 'static>(name: &str, callback: F) { // Descarga un archivo del servidor, None si no se pudo
+    let url = format!("{}{}", ASSETS_URL, name);
+    let callback = Once(callback);
+    js! {
+        var callback = @{callback};
+        fetch(@{url}, { cache: "no-store" })
+            .then(function(response) { return response.ok ? response.text() : null; })
+            .catch(function() { return null; })
+            .then(function(text) { callback(text); });
+    }
+}
+
+fn report(errors: Vec<FileError>) {                                         // Muestra en la consola los errores de un archivo descargado
+    for err in errors.iter() {
+        let message = err.to_string();
+        js! { console.warn(@{message}); }
+    }
+}
+
+struct Loader {                                                             // Archivos descargados y los que faltan por descargar
+    assets: Assets,
+    pending: usize,
+    levels: Option<String>,
+    done: Option<Box<dyn FnOnce(Assets)>>,
+}
+
+fn load_assets<F: FnOnce(Assets) + 'static>(done: F) {                     // Descarga los archivos del juego y llama a done con ellos
+    fetch_text("levels.txt", move |levels| {                                // Primero los niveles, que indican qué mapas se necesitan
+        let mut names: Vec<String> = assets::FILES.iter().filter(|n| **n != "levels.txt").map(|n| n.to_string()).collect();
+        if let Some(text) = levels.as_ref() {
+            names.extend(assets::referenced_maps(text));
+        }
+        let loader = Rc::new(RefCell::new(Loader {
+            assets: Assets::embedded(),
+            pending: names.len(),
+            levels,
+            done: Some(Box::new(done)),
+        }));
+        for name in names {
+            let loader = loader.clone();
+            fetch_text(&name.clone(), move |text| {
+                let mut loader = loader.borrow_mut();
+                if let Some(text) = text {
+                    report(loader.assets.insert(&name, text));
+                }
+                loader.pending -= 1;
+                if loader.pending > 0 {
+                    return;
+                }
+                if let Some(text) = loader.levels.take() {                  // Los niveles se revisan con los mapas ya cargados
+                    report(loader.assets.insert("levels.txt", text));
+                }
+                if let Some(done) = loader.done.take() {
+                    done(loader.assets.clone());
+                }
+            });
+        }
+    });
+}
+
+fn reload_assets(character: Rc<RefCell<Character>>) {                     // Descarga otra vez los archivos y los cambia en la partida actual
+    load_assets(move |assets| {
+        if let Err(err) = character.borrow_mut().reload(assets) {
+            let message = format!("No se recargaron los archivos: {}", err);
+            js! { console.warn(@{message}); }
+        }
+    });
+}
+
+pub fn run() {                                                              // Función que contiene la aplicación web
+    stdweb::initialize();                                                   // Función que inicia la aplicación web
+
+    load_assets(start);                                                     // El juego empieza cuando se descargan los archivos
+
+    stdweb::event_loop();                                                   // Se ejecuta un loop de la aplicación web
+}
+
+fn start(assets: Assets) {                                                  // Función que crea al jugador y los canvas y arranca el loop del juego
+    let campaign = Campaign::from_assets(&assets).unwrap_or_else(|_| Campaign::embedded());
+    let random = Box::new(XorShift::new(js_seed()));
+    let character = Rc::new(RefCell::new(Character::with_assets(campaign, assets, random))); // Se crea una instancia de la clase Character para crear al jugador, se utiliza con RefCell para poder usarlo sin problemas de Ownership de Rust
+    let canvas = Canvas::fitted("#canvas", character.borrow().view_width(), character.borrow().height()); // Se crea una instancia de la clase Canvas del tamaño del mapa para almacenar la pantalla de juego
+    let graph = Canvas::new("#graph", 10, 5);                               // Se crea una instancia de la clase Canvas para almacenar la máquina de estados y sus transiciones
+
+    stdweb::web::document().add_event_listener( {                           // Se usa esta función para agregar un escuchador de eventos, en este caso de crean los eventos de tipo KeyDown
+        let character = character.clone();                                  // Se obtiene un duplicado del objeto del jugador para usarlo sin problemas
+        move |event: KeyDownEvent| {                                        // Se declara el evento KeyDown
+            let input = match event.key().as_ref() {                        // Se usa match a modo de SWITCH (en C) para comparara qué tecla fue presionada
+                "s" => return save_snapshot(&character.borrow()),           // Se guarda la partida
+                "l" => return load_snapshot(&mut character.borrow_mut()),   // Se reanuda la partida guardada
+                "r" => return reload_assets(character.clone()),             // Se recargan los archivos del juego
+                "t" => return character.borrow_mut().next_theme(),          // Se cambia el tema de colores
+                "Escape" | "p" => Input::Pause,                             // Se pausa o se reanuda el juego
+                "ArrowLeft" => Input::LeftDown,                             // y se traduce al evento que entiende el jugador
+                "ArrowRight" => Input::RightDown,
+                "ArrowDown" => Input::DownDown,
+                "ArrowUp" => Input::UpDown,
+                _ => return,                                                // Cualquier otra tecla se ignora
+            };
+            character.borrow_mut().apply_input(input);                      // El jugador sólo toma en cuenta las teclas presionadas mientras se juega (o se pausa)
+        }
+    });
+
+    stdweb::web::document().add_event_listener( {                           // Mismo caso que la definición del escuchador de eventos anterior
+        let character = character.clone();                                  // con al diferencia de que en este se declaran los eventos
+        move |event: KeyUpEvent| {                                          // de tipo KeyUp (cuando se levanta una tecla).
+            let input = match event.key().as_ref() {
+                "ArrowLeft" => Input::LeftUp,
+                "ArrowRight" => Input::RightUp,
+                "ArrowDown" => Input::DownUp,
+                "ArrowUp" => Input::UpUp,
+                " " => Input::Start,
+                _ => return,
+            };
+            character.borrow_mut().apply_input(input);
+        }
+    });
+                                                                            // El loop del juego, donde se corren las funciones que ejecutan el juego
+    fn game_loop(character: Rc<RefCell<Character>>, graph: Rc<RefCell<Canvas>>, last_update: Rc<Cell<f64>>, time: u32){
+        stdweb::web::set_timeout(move || {                                  // Se define como función recursiva liberando memoria por periodos de tiemp
+                game_loop(                                                  // La función se vuelve a llamar
+                    character.clone(),                                      // Recibe un duplicado del jugador
+                    graph.clone(),                                          // Un dulpicado del canvas del status
+                    last_update.clone(),                                    // La hora del último update
+                    time                                                    // Y el lapso de tiempo a esperar para la siguiente iteración en milisegundos
+                );
+                character.borrow_mut().update();                            // Se ejecuta un update en el jugador
+                last_update.set(stdweb::web::Date::now());
+                let character = character.borrow();
+                if character.graph_changed() {                              // Y si hubo un cambio de estado se actualiza el status
+                    character.draw_graph(&mut *graph.borrow_mut());
+                }
+            },
+            time,                                                           // La iteración se repite en intervalos definidos por time
+        );
+    }
+                                                                            // El loop de dibujo, una vez por cuadro del navegador
+    fn draw_loop(character: Rc<RefCell<Character>>, canvas: Rc<RefCell<Canvas>>, last_update: Rc<Cell<f64>>, time: u32){
+        stdweb::web::window().request_animation_frame(move |_| {
+            let alpha = (stdweb::web::Date::now() - last_update.get()) / time as f64; // Fracción del update que ya pasó
+            character.borrow().draw_interpolated(&mut *canvas.borrow_mut(), alpha);
+            draw_loop(character, canvas, last_update, time);
+        });
+    }
+
+    let last_update = Rc::new(Cell::new(stdweb::web::Date::now()));
+    draw_loop(character.clone(), Rc::new(RefCell::new(canvas)), last_update.clone(), 45); // El primer cuadro pide los siguientes
+    game_loop(character, Rc::new(RefCell::new(graph)), last_update, 45);   // Primera llamada a la función game_loop(...);
+}